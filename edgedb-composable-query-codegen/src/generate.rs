@@ -0,0 +1,480 @@
+//! Rust source for a [`Schema`]: a selector struct per object type, an enum per scalar enum.
+
+use std::{collections::HashSet, fmt::Write};
+
+use crate::esdl::{ObjectType, Pointer, Schema};
+
+/// Strict and reserved keywords, which can't be field names
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// `other::some_thing` -> `OtherSomeThing`
+fn rust_type_name(name: &str) -> String {
+    let mut res = String::new();
+
+    for part in name.split(|c: char| !c.is_alphanumeric()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            res.extend(first.to_uppercase());
+            res.push_str(chars.as_str());
+        }
+    }
+
+    if res.is_empty() || res.starts_with(|c: char| c.is_ascii_digit()) {
+        res.insert(0, 'V');
+    }
+
+    res
+}
+
+/// The Rust type a `std` scalar decodes into, see `EdgedbPrim`
+fn rust_prim(scalar: &str) -> Option<&'static str> {
+    Some(match scalar {
+        "str" => "String",
+        "int16" => "i16",
+        "int32" => "i32",
+        "int64" => "i64",
+        "float32" => "f32",
+        "float64" => "f64",
+        "bool" => "bool",
+        "uuid" => "Uuid",
+        "datetime" => "Datetime",
+        _ => return None,
+    })
+}
+
+/// Own and inherited pointers, parents' first
+fn all_pointers<'a>(schema: &'a Schema, ty: &'a ObjectType, into: &mut Vec<&'a Pointer>) {
+    for parent in ty
+        .extending
+        .iter()
+        .filter_map(|p| schema.object_types.get(p))
+    {
+        all_pointers(schema, parent, into);
+    }
+
+    for p in &ty.pointers {
+        match into.iter().position(|q| q.name == p.name) {
+            Some(i) => into[i] = p,
+            None => into.push(p),
+        }
+    }
+}
+
+/// Whether `to` can be reached from `from` by following links
+fn links_to(schema: &Schema, from: &ObjectType, to: &str, seen: &mut HashSet<String>) -> bool {
+    if from.name == to {
+        return true;
+    }
+
+    if !seen.insert(from.name.clone()) {
+        return false;
+    }
+
+    let mut pointers = vec![];
+    all_pointers(schema, from, &mut pointers);
+
+    pointers
+        .iter()
+        .filter(|p| p.is_link)
+        .filter_map(|p| schema.object_type(&p.target))
+        .any(|target| links_to(schema, target, to, seen))
+}
+
+#[derive(Default)]
+struct Imports {
+    uuid: bool,
+    datetime: bool,
+    refs: bool,
+    id_only: bool,
+    enums: bool,
+}
+
+/// For a link, the Rust type, and why it's only the `id` if it is
+fn link_type(
+    schema: &Schema,
+    ty: &ObjectType,
+    p: &Pointer,
+    imports: &mut Imports,
+) -> (String, Option<String>) {
+    imports.refs = true;
+
+    let Some(target) = schema.object_type(&p.target) else {
+        imports.id_only = true;
+        return (
+            "Ref<IdOnly>".to_string(),
+            Some(format!("link to `{}`, which isn't in the schema", p.target)),
+        );
+    };
+
+    // selecting the target in full would select this type again, and so on
+    if links_to(schema, target, &ty.name, &mut HashSet::new()) {
+        imports.id_only = true;
+        return (
+            "Ref<IdOnly>".to_string(),
+            Some(format!(
+                "link to [`{}`], only the `id`: it links back to `{}`",
+                rust_type_name(&target.name),
+                ty.name
+            )),
+        );
+    }
+
+    (format!("Ref<{}>", rust_type_name(&target.name)), None)
+}
+
+fn property_type(schema: &Schema, p: &Pointer, imports: &mut Imports) -> Result<String, String> {
+    if p.target.is_empty() {
+        return Err("computed, its type is unknown".to_string());
+    }
+
+    if schema
+        .scalar_types
+        .get(&p.target)
+        .is_some_and(|s| s.enum_values.is_some())
+    {
+        return Ok(rust_type_name(&p.target));
+    }
+
+    let ty = schema
+        .base_scalar(&p.target)
+        .and_then(rust_prim)
+        .ok_or_else(|| format!("{} has no EdgedbPrim type", p.target))?;
+
+    match ty {
+        "Uuid" => imports.uuid = true,
+        "Datetime" => imports.datetime = true,
+        _ => {}
+    }
+
+    Ok(ty.to_string())
+}
+
+/// Pointers that can't be generated are added to `errors`, as `Type.pointer: reason`
+fn write_struct(
+    buf: &mut String,
+    schema: &Schema,
+    ty: &ObjectType,
+    imports: &mut Imports,
+    errors: &mut Vec<String>,
+) -> std::fmt::Result {
+    let mut pointers = vec![];
+    all_pointers(schema, ty, &mut pointers);
+
+    imports.uuid = true;
+
+    writeln!(buf, "/// `{}`", ty.name)?;
+    writeln!(
+        buf,
+        "#[derive(Debug, PartialEq, EdgedbObject, EdgedbComposableSelector)]"
+    )?;
+    writeln!(buf, "pub struct {} {{", rust_type_name(&ty.name))?;
+    writeln!(buf, "    pub id: Uuid,")?;
+
+    for p in pointers {
+        let (elem, doc) = if p.is_link {
+            link_type(schema, ty, p, imports)
+        } else {
+            match property_type(schema, p, imports) {
+                Ok(elem) => (elem, None),
+                Err(reason) => {
+                    errors.push(format!("{}.{}: {reason}", ty.name, p.name));
+                    continue;
+                }
+            }
+        };
+
+        let field_ty = match (p.multi, p.required) {
+            (true, _) => format!("Vec<{elem}>"),
+            (false, true) => elem,
+            (false, false) => format!("Option<{elem}>"),
+        };
+
+        if let Some(doc) = doc {
+            writeln!(buf, "    /// {doc}")?;
+        }
+
+        if RUST_KEYWORDS.contains(&p.name.as_str()) {
+            writeln!(buf, "    #[var(\".{}\")]", p.name)?;
+            writeln!(buf, "    pub {}_: {field_ty},", p.name)?;
+        } else {
+            writeln!(buf, "    pub {}: {field_ty},", p.name)?;
+        }
+    }
+
+    writeln!(buf, "}}")
+}
+
+fn write_enum(buf: &mut String, name: &str, values: &[String]) -> std::fmt::Result {
+    let ident = rust_type_name(name);
+    let variants = values
+        .iter()
+        .map(|v| (v, rust_type_name(v)))
+        .collect::<Vec<_>>();
+
+    writeln!(buf, "/// `{name}`")?;
+    writeln!(buf, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]")?;
+    writeln!(buf, "pub enum {ident} {{")?;
+    for (_, variant) in &variants {
+        writeln!(buf, "    {variant},")?;
+    }
+    writeln!(buf, "}}\n")?;
+
+    writeln!(buf, "impl EdgedbPrim for {ident} {{")?;
+    writeln!(buf, "    const TYPE_CAST: &'static str = \"{name}\";\n")?;
+    writeln!(
+        buf,
+        "    fn from_edgedb_val(value: Value) -> anyhow::Result<Self> {{"
+    )?;
+    writeln!(buf, "        match value {{")?;
+    writeln!(buf, "            Value::Enum(v) => match &*v {{")?;
+    for (value, variant) in &variants {
+        writeln!(buf, "                {value:?} => Ok(Self::{variant}),")?;
+    }
+    writeln!(
+        buf,
+        "                v => anyhow::bail!(\"unexpected {name} value: {{v}}\"),"
+    )?;
+    writeln!(buf, "            }},")?;
+    writeln!(
+        buf,
+        "            v => anyhow::bail!(\"expected {name}, got {{v:?}}\"),"
+    )?;
+    writeln!(buf, "        }}")?;
+    writeln!(buf, "    }}\n")?;
+    writeln!(
+        buf,
+        "    fn to_edgedb_val(self) -> anyhow::Result<Value> {{"
+    )?;
+    writeln!(buf, "        Ok(Value::Enum(")?;
+    writeln!(buf, "            match self {{")?;
+    for (value, variant) in &variants {
+        writeln!(buf, "                Self::{variant} => {value:?},")?;
+    }
+    writeln!(buf, "            }}")?;
+    writeln!(buf, "            .into(),")?;
+    writeln!(buf, "        ))")?;
+    writeln!(buf, "    }}")?;
+    writeln!(buf, "}}\n")?;
+
+    writeln!(buf, "impl EdgedbValue for {ident} {{")?;
+    writeln!(buf, "    type NativeArgType = Self;\n")?;
+    writeln!(
+        buf,
+        "    fn from_edgedb_value(value: Value) -> anyhow::Result<Self> {{"
+    )?;
+    writeln!(buf, "        Self::from_edgedb_val(value)")?;
+    writeln!(buf, "    }}")?;
+    writeln!(buf, "}}")
+}
+
+/// Rust source with a `#[derive(EdgedbObject, EdgedbComposableSelector)]` struct for every
+/// object type, and an `EdgedbPrim` enum for every scalar enum. Links are `Ref<Target>`,
+/// or `Ref<IdOnly>` when the target links back to the type, so selecting it ends.
+///
+/// Fails with every pointer that has no Rust type: computed ones without a declared type,
+/// and scalars without an `EdgedbPrim` implementation.
+pub fn generate(schema: &Schema) -> Result<String, String> {
+    let mut imports = Imports::default();
+    let mut errors = vec![];
+    let mut items = vec![];
+
+    let mut enums = schema
+        .scalar_types
+        .values()
+        .filter_map(|s| Some((&s.name, s.enum_values.as_ref()?)))
+        .collect::<Vec<_>>();
+    enums.sort();
+
+    for (name, values) in enums {
+        imports.enums = true;
+
+        let mut buf = String::new();
+        write_enum(&mut buf, name, values).unwrap();
+        items.push(buf);
+    }
+
+    let mut types = schema.object_types.values().collect::<Vec<_>>();
+    types.sort_by_key(|t| &t.name);
+
+    for ty in types {
+        let mut buf = String::new();
+        write_struct(&mut buf, schema, ty, &mut imports, &mut errors).unwrap();
+        items.push(buf);
+    }
+
+    if !errors.is_empty() {
+        return Err(format!(
+            "can't generate these pointers:\n  {}",
+            errors.join("\n  ")
+        ));
+    }
+
+    let mut buf =
+        String::from("// Generated by edgedb-composable-query-codegen, don't edit by hand.\n\n");
+
+    let mut uses = vec!["composable::EdgedbComposableSelector", "EdgedbObject"];
+    if imports.enums {
+        uses.extend(["EdgedbPrim", "EdgedbValue"]);
+    }
+    if imports.id_only {
+        uses.push("IdOnly");
+    }
+    if imports.refs {
+        uses.push("Ref");
+    }
+    let use_line = format!("use edgedb_composable_query::{{{}}};\n", uses.join(", "));
+    if use_line.len() <= 100 {
+        buf.push_str(&use_line);
+    } else {
+        buf.push_str(&format!(
+            "use edgedb_composable_query::{{\n    {},\n}};\n",
+            uses.join(", ")
+        ));
+    }
+
+    let mut models = vec![];
+    if imports.datetime {
+        models.push("Datetime");
+    }
+    if imports.uuid {
+        models.push("Uuid");
+    }
+    let mut protocol = match &*models {
+        [] => vec![],
+        [model] => vec![format!("model::{model}")],
+        models => vec![format!("model::{{{}}}", models.join(", "))],
+    };
+    if imports.enums {
+        protocol.push("value::Value".to_string());
+    }
+    match &*protocol {
+        [] => {}
+        [path] => buf.push_str(&format!("use edgedb_protocol::{path};\n")),
+        // like rustfmt, nested groups go on their own lines
+        paths if paths.iter().any(|p| p.contains('{')) => buf.push_str(&format!(
+            "use edgedb_protocol::{{\n    {},\n}};\n",
+            paths.join(",\n    ")
+        )),
+        paths => buf.push_str(&format!("use edgedb_protocol::{{{}}};\n", paths.join(", "))),
+    }
+
+    for item in items {
+        buf.push('\n');
+        buf.push_str(&item);
+    }
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate, rust_type_name};
+    use crate::esdl::Schema;
+
+    #[test]
+    fn type_names() {
+        assert_eq!(rust_type_name("Inner"), "Inner");
+        assert_eq!(rust_type_name("other::some_thing"), "OtherSomeThing");
+        assert_eq!(rust_type_name("Not Active"), "NotActive");
+        assert_eq!(rust_type_name("2fa"), "V2fa");
+    }
+
+    #[test]
+    fn insta_generated() {
+        let mut schema = Schema::default();
+        schema
+            .parse_into(
+                r#"
+                module default {
+                    scalar type Status extending enum<Active, 'Not Active'>;
+                    scalar type Slug extending str;
+
+                    abstract type Named {
+                        required name: str;
+                    }
+
+                    type Inner {
+                        required req: str;
+                        opt: str;
+                        status: Status;
+                    }
+
+                    type Outer extending Named {
+                        inner: Inner;
+                        multi items: Inner;
+                        parent: Outer;
+                        owner: other::User;
+                        required slug: Slug;
+                        multi tags: str;
+                        type: int32;
+                        created_at: datetime;
+                    }
+                }
+                "#,
+            )
+            .unwrap();
+
+        insta::assert_snapshot!(generate(&schema).unwrap());
+    }
+
+    #[test]
+    fn keyword_fields() {
+        let mut schema = Schema::default();
+        schema
+            .parse_into(
+                r#"
+                module default {
+                    type Outer {
+                        abstract: bool;
+                        macro: str;
+                        virtual: bool;
+                        union: str;
+                    }
+                }
+                "#,
+            )
+            .unwrap();
+
+        let generated = generate(&schema).unwrap();
+
+        for keyword in ["abstract", "macro", "virtual"] {
+            assert!(
+                generated.contains(&format!("    #[var(\".{keyword}\")]\n    pub {keyword}_: "))
+            );
+        }
+
+        // a weak keyword is a valid field name
+        assert!(generated.contains("    pub union: "));
+    }
+
+    #[test]
+    fn unsupported_pointers() {
+        let mut schema = Schema::default();
+        schema
+            .parse_into(
+                r#"
+                module default {
+                    type Outer {
+                        required name: str;
+                        timeout: duration;
+                        name_len := len(.name);
+                    }
+                }
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            generate(&schema).unwrap_err(),
+            "can't generate these pointers:\n  \
+             Outer.timeout: duration has no EdgedbPrim type\n  \
+             Outer.name_len: computed, its type is unknown"
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! # edgedb-composable-query-codegen
+//!
+//! Generates [edgedb-composable-query](https://docs.rs/edgedb-composable-query) selector structs
+//! from an EdgeDB schema, either from the `dbschema/*.esdl` files, or from an introspection
+//! dump (see [`introspection::INTROSPECTION_QUERY`]).
+//!
+//! ```
+//! use edgedb_composable_query_codegen::{esdl::Schema, generate};
+//!
+//! let mut schema = Schema::default();
+//! schema.parse_into("module default { type Inner { required req: str; opt: str; } }")?;
+//!
+//! let code = generate(&schema)?;
+//! assert!(code.contains("pub opt: Option<String>,"));
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! Or with the binary:
+//!
+//! ```sh
+//! cargo run -p edgedb-composable-query-codegen -- dbschema > src/schema.rs
+//! cargo run -p edgedb-composable-query-codegen -- --json schema.json > src/schema.rs
+//! ```
+
+mod generate;
+
+pub use edgedb_composable_query_schema::{esdl, introspection};
+
+pub use generate::generate;
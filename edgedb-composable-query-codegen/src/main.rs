@@ -0,0 +1,50 @@
+use anyhow::Context;
+use edgedb_composable_query_codegen::{esdl::load_schema, generate, introspection};
+
+const USAGE: &str = "usage: edgedb-composable-query-codegen [<dbschema dir> | --json <introspection.json> | --print-query] [-o <out.rs>]";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+
+    let mut dir = None;
+    let mut json = None;
+    let mut out = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = Some(args.next().context(USAGE)?),
+            "-o" => out = Some(args.next().context(USAGE)?),
+            "--print-query" => {
+                println!("{}", introspection::INTROSPECTION_QUERY);
+                return Ok(());
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ if dir.is_none() => dir = Some(arg),
+            _ => anyhow::bail!(USAGE),
+        }
+    }
+
+    let schema = match json {
+        Some(path) => {
+            let src = std::fs::read_to_string(&path).with_context(|| format!("reading {path}"))?;
+            introspection::schema_from_json(&src).with_context(|| format!("parsing {path}"))?
+        }
+        None => {
+            load_schema(dir.as_deref().unwrap_or("dbschema").as_ref())
+                .map_err(anyhow::Error::msg)?
+                .schema
+        }
+    };
+
+    let code = generate(&schema).map_err(anyhow::Error::msg)?;
+
+    match out {
+        Some(path) => std::fs::write(&path, code).with_context(|| format!("writing {path}"))?,
+        None => print!("{code}"),
+    }
+
+    Ok(())
+}
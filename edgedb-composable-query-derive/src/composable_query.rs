@@ -12,10 +12,36 @@ pub fn derive_composable_query_impl(
     // let selector = &query.result;
     let ident = &item.ident;
 
+    let aggregate = query.aggregate_tokens().map(|format_aggregate| {
+        quote! {
+            impl ::edgedb_composable_query::composable::EdgedbSelectQuery for #ident {
+                #format_aggregate
+            }
+        }
+    });
+
+    let page = query.page_tokens().map(|page| {
+        quote! {
+            impl ::edgedb_composable_query::composable::EdgedbPaginatedQuery for #ident {
+                #page
+            }
+        }
+    });
+
     Ok(quote! {
         impl ::edgedb_composable_query::composable::EdgedbComposableQuery for #ident {
             #query
         }
+
+        #aggregate
+
+        #page
+
+        ::edgedb_composable_query::__inventory::submit! {
+            ::edgedb_composable_query::composable::RegisteredQuery::new::<#ident>(
+                concat!(module_path!(), "::", stringify!(#ident))
+            )
+        }
     })
 }
 
@@ -115,6 +141,222 @@ mod test {
         insta::assert_snapshot!(formatted);
     }
 
+    #[test]
+    fn insta_test_clauses() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[params(req: String, n: i64)]
+            #[select("Inner")]
+            #[filter(".req = req")]
+            #[filter("exists .opt")]
+            #[order_by(".req desc")]
+            #[order_by(".opt")]
+            #[offset(10)]
+            #[limit(n)]
+            struct InnersByReq(Vec<Inner>);
+
+        };
+
+        let formatted = on_one_quote(input);
+
+        insta::assert_snapshot!(formatted);
+    }
+
+    #[test]
+    fn clause_duplicates() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[select("Inner")]
+            #[limit(1)]
+            #[limit(2)]
+            struct OneInner(Inner);
+
+        };
+
+        let err = derive_composable_query_for_test(input).unwrap_err();
+
+        assert_eq!(err.to_string(), "expected at most one #[limit] attribute");
+    }
+
+    #[test]
+    fn nested_without_select() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            struct Outer {
+                #[nested(limit = 1)]
+                inner: Inner,
+            }
+
+        };
+
+        let err = derive_composable_query_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected #[select] on a query with #[nested] fields"
+        );
+    }
+
+    #[test]
+    fn backlink_without_select() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            struct Inner {
+                #[backlink(Outer.inner)]
+                outers: Vec<Outer>,
+            }
+
+        };
+
+        let err = derive_composable_query_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected #[select] on a query with #[backlink] fields"
+        );
+    }
+
+    #[test]
+    fn recursive_without_select() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            struct Category {
+                #[recursive(depth = 3)]
+                children: Vec<Category>,
+            }
+
+        };
+
+        let err = derive_composable_query_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected #[select] on a query with #[recursive] fields"
+        );
+    }
+
+    #[test]
+    fn insta_test_group() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[params(req: String)]
+            #[select("select Inner filter .req = req")]
+            #[group(by = ".opt")]
+            struct InnersByOpt(Vec<Grouped<ByOpt, Inner>>);
+
+        };
+
+        let formatted = on_one_quote(input);
+
+        insta::assert_snapshot!(formatted);
+    }
+
+    #[test]
+    fn insta_test_paginate() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[params(opt: String)]
+            #[select("Inner")]
+            #[filter(".opt = opt")]
+            #[paginate(by = "req")]
+            struct InnersByReq(Vec<Inner>);
+
+        };
+
+        let formatted = on_one_quote(input);
+
+        insta::assert_snapshot!(formatted);
+    }
+
+    #[test]
+    fn insta_test_type_intersection() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[select("Content")]
+            struct ContentSelector {
+                title: String,
+                #[is(Video)]
+                duration: Option<i64>,
+                #[is(Video)]
+                #[nested(order_by = ".title")]
+                chapters: Vec<ChapterSelector>,
+            }
+
+        };
+
+        let formatted = on_one_quote(input);
+
+        insta::assert_snapshot!(formatted);
+    }
+
+    #[test]
+    fn paginate_with_limit() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[select("Inner")]
+            #[paginate(by = "req")]
+            #[limit(10)]
+            struct InnersByReq(Vec<Inner>);
+
+        };
+
+        let err = derive_composable_query_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected no #[order_by], #[offset], #[limit] or #[group] with #[paginate]"
+        );
+    }
+
+    #[test]
+    fn splat_without_select() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[splat]
+            struct OuterAdminView {
+                other_field: String,
+            }
+
+        };
+
+        let err = derive_composable_query_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected #[select] on a query with #[splat]"
+        );
+    }
+
+    #[test]
+    fn group_with_clauses() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[select("Inner")]
+            #[group(by = ".opt")]
+            #[limit(2)]
+            struct InnersByOpt(Vec<Grouped<ByOpt, Inner>>);
+
+        };
+
+        let err = derive_composable_query_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected no #[filter], #[order_by], #[offset] or #[limit] with #[group]"
+        );
+    }
+
     #[test]
     fn insta_test_empty_struct() {
         let input = quote! {
@@ -186,6 +428,21 @@ mod test {
         insta::assert_snapshot!(formatted);
     }
 
+    #[test]
+    fn missing_query_file() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableQuery)]
+            #[select(file = "queries/missing.edgeql")]
+            struct MissingFile(InnerSelector);
+
+        };
+
+        let err = derive_composable_query_for_test(input).unwrap_err();
+
+        assert!(err.to_string().contains("couldn't find query file"));
+    }
+
     #[test]
     fn insta_test_wrapper() {
         let input = quote! {
@@ -1,5 +1,10 @@
-use crate::{opts::ComposableQueryOpts, query::QueryVar, tokens::ComposableQueryAttribute};
-use darling::FromDeriveInput;
+use crate::{
+    opts::{ComposableQueryOpts, EdgedbTypeAttr},
+    query::QueryVar,
+    schema::check_selector,
+    tokens::ComposableQueryAttribute,
+};
+use darling::{ast, FromDeriveInput};
 use quote::quote;
 use syn::DeriveInput;
 
@@ -22,10 +27,79 @@ pub fn derive_composable_selector_impl(
 
     let result_type = selector.as_composable_query_result_type();
 
+    let edgedb_attr = EdgedbTypeAttr::from_attrs(&item.attrs)?;
+
+    let schema_deps = match &edgedb_attr.type_name {
+        Some(type_name) => {
+            let splat = item.attrs.iter().any(|a| a.path().is_ident("splat"));
+            check_selector(type_name, edgedb_attr.schema.as_deref(), splat, &item.data)?
+        }
+        None => quote! {},
+    };
+
+    let (target, registration) = match &edgedb_attr.type_name {
+        Some(type_name) => (
+            quote! { Some(#type_name) },
+            quote! {
+                ::edgedb_composable_query::__inventory::submit! {
+                    ::edgedb_composable_query::composable::RegisteredSelector::new::<#ident>(
+                        concat!(module_path!(), "::", stringify!(#ident)),
+                        #type_name,
+                    )
+                }
+            },
+        ),
+        None => (quote! { None }, quote! {}),
+    };
+
+    let fields = match &item.data {
+        ast::Data::Struct(fields) => fields
+            .iter()
+            .filter(|f| f.var.is_none() && f.backlink.is_none() && f.is.is_none() && !f.flatten)
+            .filter_map(|f| {
+                let name = f.field_name.as_ref()?;
+                let ty = &f.ty;
+
+                Some(match f.recursive {
+                    Some(_) => quote! {
+                        ::edgedb_composable_query::composable::SelectorField::recursive::<#ty>(#name)
+                    },
+                    None => quote! {
+                        ::edgedb_composable_query::composable::SelectorField::new::<#ty>(#name)
+                    },
+                })
+            })
+            .collect(),
+        ast::Data::Enum(_) => vec![],
+    };
+
+    let flattened = match &item.data {
+        ast::Data::Struct(fields) => fields.iter().filter(|f| f.flatten).map(|f| &f.ty).collect(),
+        ast::Data::Enum(_) => vec![],
+    };
+
+    // the fields of the `#[flatten]`ed selectors are selected from the same type
+    let fields = match flattened.is_empty() {
+        true => quote! { vec![#(#fields),*] },
+        false => quote! {
+            let mut fields = vec![#(#fields),*];
+            #(
+                fields.extend(
+                    <#flattened as ::edgedb_composable_query::composable::EdgedbComposableSelector>::fields()
+                );
+            )*
+            fields
+        },
+    };
+
     Ok(quote! {
+        #schema_deps
+
         impl ::edgedb_composable_query::composable::EdgedbComposableSelector for #ident {
             const RESULT_TYPE: ::edgedb_composable_query::composable::ComposableQueryResultKind =
                 #result_type;
+            const TARGET: ::edgedb_composable_query::composable::SelectorTarget =
+                ::edgedb_composable_query::composable::SelectorTarget::Object(#target);
 
             fn format_selector(fmt: &mut impl ::std::fmt::Write) -> Result<(), std::fmt::Error> {
                 use ::edgedb_composable_query::__itertools::Itertools;
@@ -34,7 +108,13 @@ pub fn derive_composable_selector_impl(
 
                 Ok(())
             }
+
+            fn fields() -> Vec<::edgedb_composable_query::composable::SelectorField> {
+                #fields
+            }
         }
+
+        #registration
     })
 }
 
@@ -92,4 +172,312 @@ mod test {
 
         insta::assert_snapshot!(formatted);
     }
+
+    #[test]
+    fn doc_comments() {
+        let input = quote! {
+
+            /// `Inner`
+            #[derive(EdgedbComposableSelector)]
+            struct Inner {
+                /// required
+                req: String,
+            }
+
+        };
+
+        assert!(derive_composable_selector_for_test(input).is_ok());
+    }
+
+    #[test]
+    fn nested_on_var() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            struct Outer {
+                #[var(".inner")]
+                #[nested(limit = 1)]
+                inner: Inner,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected #[nested] on a nested selector, not on a #[var] field at inner"
+        );
+    }
+
+    #[test]
+    fn nested_selector_without_select() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            struct Outer {
+                #[nested(limit = 1)]
+                inner: Inner,
+            }
+
+        };
+
+        let formatted = on_one_quote_selector(input);
+
+        insta::assert_snapshot!(formatted);
+    }
+
+    #[test]
+    fn recursive_syntax() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            struct Category {
+                #[recursive(levels = 3)]
+                children: Vec<Category>,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert_eq!(err.to_string(), "expected `depth` at children");
+    }
+
+    #[test]
+    fn recursive_selector_without_select() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            struct Category {
+                #[recursive(depth = 3)]
+                children: Vec<Category>,
+            }
+
+        };
+
+        let formatted = on_one_quote_selector(input);
+
+        insta::assert_snapshot!(formatted);
+    }
+
+    #[test]
+    fn splat_syntax() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[splat(all)]
+            struct Outer {
+                other_field: String,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert_eq!(err.to_string(), "expected `deep`");
+    }
+
+    #[test]
+    fn splat_on_wrapper() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[select("Outer")]
+            #[splat]
+            struct Outers(Vec<Outer>);
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected #[splat] on a struct with named fields"
+        );
+    }
+
+    #[test]
+    fn backlink_syntax() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            struct Inner {
+                #[backlink(Outer)]
+                outers: Vec<Outer>,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert_eq!(err.to_string(), "expected #[backlink(Type.link)] at outers");
+    }
+
+    #[test]
+    fn schema_missing_subtype() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[edgedb(type = "Outer", schema = "../dbschema")]
+            struct OuterSelector {
+                other_field: String,
+                #[is(Podcast)]
+                duration: Option<i64>,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert_eq!(err.to_string(), "no type `Podcast` in the schema");
+    }
+
+    #[test]
+    fn schema_checked_selector() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[edgedb(type = "Outer", schema = "../dbschema")]
+            struct OuterSelector {
+                id: Uuid,
+                other_field: String,
+                some_field: Option<String>,
+                inner: Option<InnerSelector>,
+
+                #[var("len(.other_field)")]
+                strlen: i64,
+            }
+
+        };
+
+        let out = derive_composable_selector_for_test(input)
+            .unwrap()
+            .to_string();
+
+        assert!(out.contains("include_str"));
+        assert!(out.contains("default.esdl"));
+    }
+
+    #[test]
+    fn schema_mismatches() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[edgedb(type = "Outer", schema = "../dbschema")]
+            struct OuterSelector {
+                other_feild: String,
+                some_field: String,
+                inner: Vec<InnerSelector>,
+                a: String,
+                b: Option<i64>,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+        let messages = err.into_iter().map(|e| e.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "`Outer` has no property or link `other_feild`",
+                "`some_field` is an optional property of `Outer`, expected `Option<_>`",
+                "`inner` is a single link of `Outer`, expected `Option<_>` or a plain type",
+                "`a` is an optional link of `Outer`, expected `Option<_>`",
+                "`b` is a link to `Inner` in `Outer`, expected a selector, not a int64",
+            ]
+        );
+    }
+
+    #[test]
+    fn schema_scalar_mismatch() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[edgedb(type = "Inner", schema = "../dbschema")]
+            struct InnerSelector {
+                req: i64,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "`req` is str in `Inner`, but this type decodes from int64"
+        );
+    }
+
+    #[test]
+    fn schema_unknown_type() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[edgedb(type = "Innr", schema = "../dbschema")]
+            struct InnerSelector {
+                req: String,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert!(err.to_string().starts_with("no type `Innr` in the schema"));
+    }
+
+    #[test]
+    fn sdl_type_not_checked() {
+        let input = quote! {
+
+            #[derive(EdgedbType, EdgedbComposableSelector)]
+            #[edgedb(sdl_type = "Person", schema = "../dbschema")]
+            struct Person {
+                name: String,
+            }
+
+        };
+
+        assert!(derive_composable_selector_for_test(input).is_ok());
+    }
+
+    #[test]
+    fn splat_extra_option_field() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[edgedb(type = "Outer", schema = "../dbschema")]
+            #[splat]
+            struct OuterAdminView {
+                other_field: String,
+                extra_field: Option<String>,
+            }
+
+        };
+
+        assert!(derive_composable_selector_for_test(input).is_ok());
+    }
+
+    #[test]
+    fn splat_extra_required_field() {
+        let input = quote! {
+
+            #[derive(EdgedbComposableSelector)]
+            #[edgedb(type = "Outer", schema = "../dbschema")]
+            #[splat]
+            struct OuterAdminView {
+                other_field: String,
+                extra_field: String,
+            }
+
+        };
+
+        let err = derive_composable_selector_for_test(input).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "`Outer` has no property or link `extra_field`"
+        );
+    }
 }
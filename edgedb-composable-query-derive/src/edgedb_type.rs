@@ -0,0 +1,140 @@
+use darling::{ast, FromDeriveInput};
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::{
+    opts::{ComposableQueryOpts, EdgedbTypeAttr},
+    query::QueryVar,
+};
+
+pub fn derive_edgedb_type_impl(item: DeriveInput) -> darling::Result<proc_macro2::TokenStream> {
+    let item = ComposableQueryOpts::from_derive_input(&item)?;
+    let attr = EdgedbTypeAttr::from_attrs(&item.attrs)?;
+    let ident = &item.ident;
+
+    let type_name = attr
+        .sdl_type
+        .or(attr.type_name)
+        .unwrap_or_else(|| ident.to_string());
+
+    let ast::Data::Struct(fields) = &item.data else {
+        return Err(darling::Error::custom("expected a struct with named fields").with_span(ident));
+    };
+
+    let mut errors = darling::Error::accumulator();
+
+    let pointers = fields
+        .iter()
+        .filter_map(|f| {
+            let Some(name) = &f.field_name else {
+                errors.push(darling::Error::custom("expected named fields").with_span(&f.ty));
+                return None;
+            };
+
+            // implicit in every type
+            if name == "id" {
+                return None;
+            }
+
+            let ty = &f.ty;
+
+            match &f.var {
+                Some(QueryVar::Var(expr)) => Some(quote! {
+                    buf.push_str(&::edgedb_composable_query::__sdl_computed(#name, #expr));
+                }),
+                Some(_) => {
+                    errors.push(
+                        darling::Error::custom("computed fields must be EdgeQL expressions")
+                            .with_span(&f.ident),
+                    );
+                    None
+                }
+                None => {
+                    let required = f.edgedb.required;
+                    let multi = f.edgedb.multi;
+                    let exclusive = f.edgedb.exclusive;
+                    let default = match &f.edgedb.default {
+                        Some(d) => quote! { Some(#d) },
+                        None => quote! { None },
+                    };
+
+                    Some(quote! {
+                        buf.push_str(&::edgedb_composable_query::__sdl_pointer::<#ty>(
+                            #name,
+                            ::edgedb_composable_query::SdlPointerAttrs {
+                                required: #required,
+                                multi: #multi,
+                                exclusive: #exclusive,
+                                default: #default,
+                            },
+                        ));
+                    })
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    errors.finish()?;
+
+    let indexes = &attr.indexes;
+
+    Ok(quote! {
+        impl ::edgedb_composable_query::EdgedbType for #ident {
+            const TYPE_NAME: &'static str = #type_name;
+
+            fn sdl() -> String {
+                let mut buf = String::new();
+
+                #(#pointers)*
+                #( buf.push_str(&::edgedb_composable_query::__sdl_index(#indexes)); )*
+
+                ::edgedb_composable_query::__sdl_type(#type_name, &buf)
+            }
+        }
+
+        impl ::edgedb_composable_query::EdgedbSdlElement for #ident {
+            const SDL_TARGET: &'static str = #type_name;
+        }
+
+        ::edgedb_composable_query::__inventory::submit! {
+            ::edgedb_composable_query::RegisteredType::new::<#ident>()
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use quote::quote;
+    use syn::DeriveInput;
+
+    use super::derive_edgedb_type_impl;
+
+    #[test]
+    fn insta_test_type() {
+        let input = quote! {
+
+            #[derive(EdgedbType)]
+            #[edgedb(sdl_type = "Person", index = ".name")]
+            struct User {
+                id: Uuid,
+                #[edgedb(exclusive)]
+                name: String,
+                #[edgedb(default = "datetime_current()")]
+                created: Option<String>,
+                #[edgedb(required)]
+                tags: Vec<String>,
+                friends: Vec<Ref<User>>,
+
+                #[var("len(.name)")]
+                name_len: i64,
+            }
+
+        };
+
+        let out = derive_edgedb_type_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap();
+
+        insta::assert_snapshot!(prettyplease::unparse(
+            &syn::parse_file(&out.to_string()).unwrap()
+        ));
+    }
+}
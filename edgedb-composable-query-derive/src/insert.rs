@@ -0,0 +1,513 @@
+use darling::{ast, FromDeriveInput};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, GenericArgument, Ident, PathArguments, Type};
+
+use crate::{
+    opts::{ComposableQueryOpts, EdgedbTypeAttr, UpsertAttr},
+    schema::optional_schema,
+};
+
+/// `Ref<T>`, or `Ref<T>` in an `Option`, `Patch`, `Vec` or `LinkOp`: a link, to a target that has to come
+/// from the schema or `#[edgedb(link)]`
+fn is_ref(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    let Some(last) = path.path.segments.last() else {
+        return false;
+    };
+
+    match last.ident.to_string().as_str() {
+        "Ref" => true,
+        "Option" | "Patch" | "Vec" | "LinkOp" => match &last.arguments {
+            PathArguments::AngleBracketed(args) => {
+                matches!(args.args.first(), Some(GenericArgument::Type(inner)) if is_ref(inner))
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// `LinkOp<_>`: only an update can add or remove links
+fn is_link_op(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.segments.last().is_some_and(|last| last.ident == "LinkOp"))
+}
+
+/// A field of an `EdgedbInsert`, `EdgedbUpdate` or `EdgedbDelete`, one parameter each
+pub struct MutationField<'a> {
+    pub name: &'a str,
+    pub ident: &'a Ident,
+    pub ty: &'a Type,
+    /// the target type, if it's a link
+    pub link: Option<String>,
+}
+
+impl MutationField<'_> {
+    /// `Some("Target")` or `None`
+    pub fn link_tokens(&self) -> TokenStream {
+        match &self.link {
+            Some(target) => quote! { Some(#target) },
+            None => quote! { None },
+        }
+    }
+}
+
+/// A mutation of `#[edgedb(type = "...")]`
+pub struct Mutation<'a> {
+    pub ident: &'a Ident,
+    pub type_name: String,
+    pub returning: Option<Type>,
+    pub fields: Vec<MutationField<'a>>,
+    /// makes the crate rebuild when the schema changes
+    pub schema_deps: Option<TokenStream>,
+}
+
+impl<'a> Mutation<'a> {
+    /// `what` is "insert", "update" or "delete", for the error messages
+    pub fn from_opts(item: &'a ComposableQueryOpts, what: &str) -> darling::Result<Self> {
+        let attr = EdgedbTypeAttr::from_attrs(&item.attrs)?;
+        let ident = &item.ident;
+
+        let Some(type_name) = attr.type_name else {
+            return Err(darling::Error::custom(format!(
+                "expected #[edgedb(type = \"...\")] with the type to {what}"
+            ))
+            .with_span(ident));
+        };
+
+        let ast::Data::Struct(fields) = &item.data else {
+            return Err(
+                darling::Error::custom("expected a struct with named fields").with_span(ident),
+            );
+        };
+
+        // link targets come from the schema when there's one
+        let (schema, schema_deps) = optional_schema(attr.schema.as_deref())?.unzip();
+        let object = schema.as_ref().and_then(|s| s.object_type(&type_name));
+
+        let mut errors = darling::Error::accumulator();
+        let mut mutation_fields = vec![];
+
+        for f in fields.iter() {
+            let (Some(name), Some(field_ident)) = (&f.field_name, &f.ident) else {
+                errors.push(darling::Error::custom("expected named fields").with_span(&f.ty));
+                continue;
+            };
+
+            if f.var.is_some() {
+                errors.push(
+                    darling::Error::custom(format!("#[var] isn't supported in {what}s"))
+                        .with_span(field_ident),
+                );
+                continue;
+            }
+
+            if what != "update" && is_link_op(&f.ty) {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "`LinkOp` is only supported in updates, not in {what}s"
+                    ))
+                    .with_span(field_ident),
+                );
+                continue;
+            }
+
+            let schema_link = schema
+                .as_ref()
+                .zip(object)
+                .and_then(|(schema, object)| schema.pointer(object, name))
+                .filter(|p| p.is_link)
+                .map(|p| p.target.clone());
+
+            let link = f.edgedb.link.clone().or(schema_link);
+
+            if link.is_none() && is_ref(&f.ty) {
+                errors.push(
+                    darling::Error::custom(
+                        "expected #[edgedb(link = \"...\")] with the link's target type, \
+                         it's not in the schema",
+                    )
+                    .with_span(field_ident),
+                );
+                continue;
+            }
+
+            mutation_fields.push(MutationField {
+                name,
+                ident: field_ident,
+                ty: &f.ty,
+                link,
+            });
+        }
+
+        errors.finish()?;
+
+        Ok(Self {
+            ident,
+            type_name,
+            returning: attr.returning,
+            fields: mutation_fields,
+            schema_deps,
+        })
+    }
+
+    /// `EdgedbComposableQuery` with `body` as `format_query`, `trait_name` with `into_args` and
+    /// `trait_items`, and the registration
+    pub fn impls(
+        &self,
+        trait_name: TokenStream,
+        trait_items: TokenStream,
+        return_type: TokenStream,
+        body: TokenStream,
+    ) -> TokenStream {
+        let ident = self.ident;
+        let schema_deps = &self.schema_deps;
+        let names = self.fields.iter().map(|f| f.name);
+        let types = self.fields.iter().map(|f| f.ty).collect::<Vec<_>>();
+        let idents = self.fields.iter().map(|f| f.ident);
+
+        let (arg_types, arg_values) = match types.len() {
+            0 => (quote! { () }, quote! { () }),
+            _ => (
+                quote! { (#( #types ),* ,) },
+                quote! { (#( self.#idents ),* ,) },
+            ),
+        };
+
+        quote! {
+            #schema_deps
+
+            impl ::edgedb_composable_query::composable::EdgedbComposableQuery for #ident {
+                const ARG_NAMES: &'static [&'static str] = &[#( #names ),*];
+
+                type ArgTypes = #arg_types;
+                type ReturnType = #return_type;
+
+                fn format_query(
+                    fmt: &mut impl ::std::fmt::Write,
+                    args: &::std::collections::HashMap<&str, String>
+                ) -> Result<(), ::std::fmt::Error> {
+                    #body
+
+                    Ok(())
+                }
+            }
+
+            impl ::edgedb_composable_query::composable::#trait_name for #ident {
+                fn into_args(self) -> Self::ArgTypes {
+                    #arg_values
+                }
+
+                #trait_items
+            }
+
+            ::edgedb_composable_query::__inventory::submit! {
+                ::edgedb_composable_query::composable::RegisteredQuery::new::<#ident>(
+                    concat!(module_path!(), "::", stringify!(#ident))
+                )
+            }
+        }
+    }
+
+    /// The `returning` selector, and the code that writes its ` { shape }`
+    pub fn returning_shape(&self) -> Option<(TokenStream, TokenStream)> {
+        let selector = self.returning.as_ref()?;
+
+        Some((
+            quote! { #selector },
+            quote! {
+                fmt.write_str(" {\n")?;
+                <#selector as ::edgedb_composable_query::composable::EdgedbComposableSelector>::format_selector(fmt)?;
+                fmt.write_str("\n}")?;
+            },
+        ))
+    }
+}
+
+/// `name := value`, `arg` is `__insert_value` of the field
+fn assignment(f: &MutationField, arg: &impl Fn(&MutationField) -> TokenStream) -> TokenStream {
+    let name = f.name;
+    let value = arg(f);
+
+    quote! {
+        fmt.write_fmt(format_args!("\t{} := {},\n", #name, #value))?;
+    }
+}
+
+/// `insert T { ... }`, and the conflict clause of an upsert
+fn insert_body(
+    mutation: &Mutation,
+    upsert: Option<&UpsertAttr>,
+    arg: impl Fn(&MutationField) -> TokenStream,
+) -> TokenStream {
+    let type_name = &mutation.type_name;
+    let assignments = mutation.fields.iter().map(|f| assignment(f, &arg));
+
+    // the conflicting object gets the other fields, the same values
+    let conflict = upsert.map(|upsert| {
+        let keys = upsert.key_fields();
+        let conflict_on = &upsert.conflict_on;
+        let updates = mutation
+            .fields
+            .iter()
+            .filter(|f| !keys.contains(&f.name))
+            .map(|f| assignment(f, &arg))
+            .collect::<Vec<_>>();
+
+        match updates.len() {
+            // nothing to update, select the existing one
+            0 => quote! {
+                fmt.write_fmt(format_args!(
+                    "\nunless conflict on {}\nelse (select {})",
+                    #conflict_on,
+                    #type_name
+                ))?;
+            },
+            _ => quote! {
+                fmt.write_fmt(format_args!(
+                    "\nunless conflict on {}\nelse (update {} set {{\n",
+                    #conflict_on,
+                    #type_name
+                ))?;
+
+                #( #updates )*
+
+                fmt.write_str("})")?;
+            },
+        }
+    });
+
+    quote! {
+        fmt.write_fmt(format_args!("insert {} {{\n", #type_name))?;
+
+        #( #assignments )*
+
+        fmt.write_str("}")?;
+
+        #conflict
+    }
+}
+
+pub fn derive_insert_impl(item: DeriveInput) -> darling::Result<proc_macro2::TokenStream> {
+    let item = ComposableQueryOpts::from_derive_input(&item)?;
+    let mutation = Mutation::from_opts(&item, "insert")?;
+    let upsert = UpsertAttr::from_attrs(&item.attrs)?;
+
+    if let Some(upsert) = &upsert {
+        if let Some(missing) = upsert
+            .key_fields()
+            .into_iter()
+            .find(|k| !mutation.fields.iter().any(|f| f.name == *k))
+        {
+            return Err(darling::Error::custom(format!(
+                "`{missing}` in conflict_on isn't a field"
+            ))
+            .with_span(mutation.ident));
+        }
+    }
+
+    let insert = insert_body(&mutation, upsert.as_ref(), |f| {
+        let MutationField { name, ty, .. } = f;
+        let link = f.link_tokens();
+
+        quote! {
+            ::edgedb_composable_query::composable::__insert_value::<#ty>(#name, #link, &args[#name], None)
+        }
+    });
+
+    // the same, with each value from a JSON object, for bulk and nested inserts
+    let json_insert = insert_body(&mutation, upsert.as_ref(), |f| {
+        let MutationField { name, ty, .. } = f;
+        let link = f.link_tokens();
+
+        quote! {
+            ::edgedb_composable_query::composable::__insert_value::<#ty>(#name, #link, "", Some(item))
+        }
+    });
+
+    let (return_type, returning) = mutation.returning_shape().unwrap_or_else(|| {
+        (
+            quote! { ::edgedb_protocol::model::Uuid },
+            quote! {
+                fmt.write_str(".id")?;
+            },
+        )
+    });
+
+    let ident = mutation.ident;
+    let impls = mutation.impls(
+        quote! { EdgedbInsert },
+        quote! {
+            fn format_json_insert(
+                fmt: &mut impl ::std::fmt::Write,
+                item: &str
+            ) -> Result<(), ::std::fmt::Error> {
+                #json_insert
+
+                Ok(())
+            }
+        },
+        return_type,
+        quote! {
+            fmt.write_str("select (")?;
+
+            #insert
+
+            fmt.write_str(")")?;
+
+            #returning
+        },
+    );
+
+    Ok(quote! {
+        #impls
+
+        // a field of another insert is a nested insert
+        impl ::edgedb_composable_query::EdgedbQueryArg for #ident {
+            const TYPE_CAST: &'static str = "json";
+            const NESTED_INSERT: Option<fn(&str) -> String> = Some(
+                ::edgedb_composable_query::composable::__nested_insert::<Self> as fn(&str) -> String
+            );
+
+            fn to_query_arg(self) -> ::edgedb_composable_query::Result<::edgedb_protocol::value::Value> {
+                ::edgedb_composable_query::composable::__insert_json(self)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use quote::quote;
+    use syn::DeriveInput;
+
+    use super::derive_insert_impl;
+
+    fn on_one_quote(input: proc_macro2::TokenStream) -> String {
+        let out = derive_insert_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap();
+
+        // without the `include_str!`s of the local dbschema
+        prettyplease::unparse(&syn::parse_file(&out.to_string()).unwrap())
+            .lines()
+            .filter(|l| !l.starts_with("const _: &str = include_str!"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn insta_test_insert() {
+        let input = quote! {
+
+            #[derive(EdgedbInsert)]
+            #[edgedb(type = "Outer", returning = "OuterSelector")]
+            struct NewOuter {
+                other_field: String,
+                some_field: Option<String>,
+                inner: Option<Ref<Inner>>,
+                #[edgedb(link = "Inner")]
+                a: Uuid,
+            }
+
+        };
+
+        insta::assert_snapshot!(on_one_quote(input));
+    }
+
+    #[test]
+    fn insta_test_upsert() {
+        let input = quote! {
+
+            #[derive(EdgedbInsert)]
+            #[edgedb(type = "Inner", returning = "InnerSelector")]
+            #[upsert(conflict_on = ".req")]
+            struct UpsertInner {
+                req: String,
+                opt: Option<String>,
+            }
+
+        };
+
+        insta::assert_snapshot!(on_one_quote(input));
+    }
+
+    #[test]
+    fn upsert_unknown_field() {
+        let input = quote! {
+
+            #[derive(EdgedbInsert)]
+            #[edgedb(type = "Inner")]
+            #[upsert(conflict_on = "(.req, .email)")]
+            struct UpsertInner {
+                req: String,
+            }
+
+        };
+
+        let err = derive_insert_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap_err();
+
+        assert_eq!(err.to_string(), "`email` in conflict_on isn't a field");
+    }
+
+    #[test]
+    fn insert_without_type() {
+        let input = quote! {
+
+            #[derive(EdgedbInsert)]
+            struct NewInner {
+                req: String,
+            }
+
+        };
+
+        let err = derive_insert_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected #[edgedb(type = \"...\")] with the type to insert"
+        );
+    }
+
+    #[test]
+    fn ref_not_in_schema() {
+        let input = quote! {
+
+            #[derive(EdgedbInsert)]
+            #[edgedb(type = "User")]
+            struct NewUser {
+                best_friend: Option<Ref<User>>,
+            }
+
+        };
+
+        let err = derive_insert_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected #[edgedb(link = \"...\")] with the link's target type, it's not in the schema"
+        );
+    }
+
+    #[test]
+    fn link_op_in_insert() {
+        let input = quote! {
+
+            #[derive(EdgedbInsert)]
+            #[edgedb(type = "Outer")]
+            struct NewOuter {
+                other_field: String,
+                #[edgedb(link = "Inner")]
+                inners: LinkOp<Ref<Inner>>,
+            }
+
+        };
+
+        let err = derive_insert_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "`LinkOp` is only supported in updates, not in inserts"
+        );
+    }
+}
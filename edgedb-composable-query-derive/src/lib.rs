@@ -1,17 +1,24 @@
 use composable_query::derive_composable_query_impl;
 use composable_selector::derive_composable_selector_impl;
+use edgedb_type::derive_edgedb_type_impl;
+use insert::derive_insert_impl;
 use object::derive_edgedb_object_impl;
 use syn::DeriveInput;
+use update::{derive_delete_impl, derive_update_impl};
 
 mod composable_query;
 mod composable_selector;
+mod edgedb_type;
+mod insert;
 mod object;
 mod opts;
 mod query;
+mod schema;
 mod selector;
 mod tokens;
+mod update;
 
-#[proc_macro_derive(EdgedbObject)]
+#[proc_macro_derive(EdgedbObject, attributes(flatten, recursive, splat))]
 pub fn derive_edgedb_object(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let item = syn::parse_macro_input!(item as DeriveInput);
 
@@ -21,7 +28,12 @@ pub fn derive_edgedb_object(item: proc_macro::TokenStream) -> proc_macro::TokenS
     }
 }
 
-#[proc_macro_derive(EdgedbComposableSelector, attributes(params, with, var))]
+#[proc_macro_derive(
+    EdgedbComposableSelector,
+    attributes(
+        params, with, var, edgedb, nested, backlink, is, flatten, recursive, splat
+    )
+)]
 pub fn derive_edgedb_composable_selector(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let item = syn::parse_macro_input!(item as DeriveInput);
 
@@ -31,7 +43,13 @@ pub fn derive_edgedb_composable_selector(item: proc_macro::TokenStream) -> proc_
     }
 }
 
-#[proc_macro_derive(EdgedbComposableQuery, attributes(params, with, var, select, direct))]
+#[proc_macro_derive(
+    EdgedbComposableQuery,
+    attributes(
+        params, with, var, select, direct, filter, order_by, offset, limit, group, paginate, splat,
+        nested, backlink, is, flatten, recursive
+    )
+)]
 pub fn derive_composable_query(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let item = syn::parse_macro_input!(item as DeriveInput);
 
@@ -40,3 +58,43 @@ pub fn derive_composable_query(item: proc_macro::TokenStream) -> proc_macro::Tok
         Err(e) => e.write_errors().into(),
     }
 }
+
+#[proc_macro_derive(EdgedbType, attributes(edgedb, var))]
+pub fn derive_edgedb_type(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = syn::parse_macro_input!(item as DeriveInput);
+
+    match derive_edgedb_type_impl(item) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.write_errors().into(),
+    }
+}
+
+#[proc_macro_derive(EdgedbInsert, attributes(edgedb, var, upsert))]
+pub fn derive_edgedb_insert(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = syn::parse_macro_input!(item as DeriveInput);
+
+    match derive_insert_impl(item) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.write_errors().into(),
+    }
+}
+
+#[proc_macro_derive(EdgedbUpdate, attributes(edgedb, var))]
+pub fn derive_edgedb_update(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = syn::parse_macro_input!(item as DeriveInput);
+
+    match derive_update_impl(item) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.write_errors().into(),
+    }
+}
+
+#[proc_macro_derive(EdgedbDelete, attributes(edgedb, var))]
+pub fn derive_edgedb_delete(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = syn::parse_macro_input!(item as DeriveInput);
+
+    match derive_delete_impl(item) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.write_errors().into(),
+    }
+}
@@ -8,15 +8,18 @@ use syn::{DeriveInput, Type};
 
 #[derive(Debug, FromField)]
 // #[darling(attributes(lorem))]
+#[darling(forward_attrs(flatten, recursive))]
 pub struct EdgedbObjectField {
     ident: Option<syn::Ident>,
     ty: Type,
+    /// `#[flatten]`: decoded from the same shape, `#[recursive]`: can be left out of it
+    attrs: Vec<syn::Attribute>,
     // #[darling(default)]
     // skip: bool,
 }
 
 #[derive(Debug, FromDeriveInput)]
-#[darling(forward_attrs(allow, doc, cfg))]
+#[darling(forward_attrs(allow, doc, cfg, splat))]
 struct EdgedbObjectOpts {
     ident: syn::Ident,
     attrs: Vec<syn::Attribute>,
@@ -29,11 +32,34 @@ pub fn derive_edgedb_object_impl(item: DeriveInput) -> darling::Result<proc_macr
         darling::Error::custom("expected struct with named fields").with_span(&item.ident)
     })?;
 
+    let (flattened, fields) = fields
+        .iter()
+        .partition::<Vec<_>, _>(|f| f.attrs.iter().any(|a| a.path().is_ident("flatten")));
+
     let field_names = fields
         .iter()
         .map(|f| f.ident.as_ref().unwrap())
         .collect::<Vec<_>>();
 
+    let flattened_names = flattened
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect::<Vec<_>>();
+    let flattened_types = flattened.iter().map(|f| &f.ty).collect::<Vec<_>>();
+
+    let field_types = fields.iter().map(|f| &f.ty);
+
+    // a `#[splat]` selects whatever the type has, so its fields may be missing too
+    let splat = item.attrs.iter().any(|a| a.path().is_ident("splat"));
+    let field_constructors = fields.iter().map(|f| {
+        match splat || f.attrs.iter().any(|a| a.path().is_ident("recursive")) {
+            true => quote! { optional },
+            false => quote! { new },
+        }
+    });
+
+    let field_indices = (0..field_names.len()).map(proc_macro2::Literal::usize_unsuffixed);
+
     let item_name = &item.ident;
 
     Ok(quote! {
@@ -43,33 +69,61 @@ pub fn derive_edgedb_object_impl(item: DeriveInput) -> darling::Result<proc_macr
             fn from_edgedb_object(
                 shape: edgedb_protocol::codec::ObjectShape,
                 mut fields: Vec<Option<edgedb_protocol::value::Value>>,
+            ) -> anyhow::Result<Self> {
+                Self::__take_from_edgedb_object(&shape, &mut fields)
+            }
+
+            fn __take_from_edgedb_object(
+                shape: &edgedb_protocol::codec::ObjectShape,
+                fields: &mut [Option<edgedb_protocol::value::Value>],
             ) -> anyhow::Result<Self> {
                 use edgedb_composable_query::EdgedbSetValue;
 
-                #(
-                    let mut #field_names = None;
-                )*;
-
-                for (i, s) in shape.elements.iter().enumerate() {
-                    match s.name.as_str() {
-                        #(
-                            stringify!(#field_names) => {
-                                #field_names = fields[i]
-                                    .take()
-                                    .map(EdgedbSetValue::from_edgedb_set_value)
-                                    .transpose()?;
-                            }
-                        )*
-                        _ => {}
-                    }
+                thread_local! {
+                    static SHAPE_PLANS: ::std::cell::RefCell<Vec<edgedb_composable_query::__ShapePlan>> =
+                        const { ::std::cell::RefCell::new(Vec::new()) };
                 }
 
+                let plan = edgedb_composable_query::__ShapePlan::cached(
+                    &SHAPE_PLANS,
+                    shape,
+                    &[#( stringify!(#field_names) ),*],
+                );
+
+                #(
+                    let #field_names = plan[#field_indices]
+                        .and_then(|i| fields[i].take())
+                        .map(EdgedbSetValue::from_edgedb_set_value)
+                        .transpose()?;
+                )*
+
+                #(
+                    let #flattened_names = <#flattened_types as edgedb_composable_query::EdgedbObject>::__take_from_edgedb_object(
+                        shape,
+                        fields,
+                    )?;
+                )*
+
                 Ok(Self {
                     #(
                         #field_names: EdgedbSetValue::interpret_possibly_missing_required_value(#field_names)?,
                     )*
+                    #( #flattened_names, )*
                 })
             }
+
+            fn object_fields() -> Vec<edgedb_composable_query::ObjectField> {
+                vec![
+                    #(
+                        edgedb_composable_query::ObjectField::#field_constructors::<#field_types>(stringify!(#field_names)),
+                    )*
+                ]
+                .into_iter()
+                #(
+                    .chain(<#flattened_types as edgedb_composable_query::EdgedbObject>::object_fields())
+                )*
+                .collect()
+            }
         }
     })
 }
@@ -121,4 +175,22 @@ mod test {
 
         insta::assert_snapshot!(formatted);
     }
+
+    #[test]
+    fn insta_test_flatten() {
+        let input = quote! {
+
+            #[derive(Debug, PartialEq, EdgedbObject)]
+            struct ExamplImplStruct {
+                a: String,
+                #[flatten]
+                audit: AuditFields,
+            }
+
+        };
+
+        let formatted = on_one_quote(input);
+
+        insta::assert_snapshot!(formatted);
+    }
 }
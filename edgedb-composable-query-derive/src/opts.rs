@@ -1,30 +1,224 @@
 use darling::{ast, util, FromDeriveInput, FromField};
-use syn::Type;
+use syn::{LitStr, Type};
 
-use crate::query::QueryVar;
+use crate::query::{Clauses, QueryVar};
 
 #[derive(Debug)]
 pub struct ComposableQueryReturn {
-    // ident: Option<syn::Ident>,
+    pub ident: Option<syn::Ident>,
     pub field_name: Option<String>,
 
     #[allow(unused)]
     pub ty: Type,
 
     pub var: Option<QueryVar>,
+
+    pub edgedb: EdgedbFieldAttr,
+
+    /// `#[nested(filter = "...", order_by = "...", offset = 5, limit = 5)]` on a nested selector
+    pub nested: Clauses,
+
+    /// `#[backlink(Post.author)]` on a nested selector
+    pub backlink: Option<Backlink>,
+
+    /// `#[is(Video)]`: the field is selected as `[is Video].field`
+    pub is: Option<String>,
+
+    /// `#[flatten]`: the fields of this selector are inlined into the shape
+    pub flatten: bool,
+
+    /// `#[recursive(depth = 3)]` on a nested selector: the levels of it to select
+    pub recursive: Option<usize>,
+}
+
+/// `#[backlink(Post.author)]`: the `Post`s linking to this object with `author`
+#[derive(Debug, Clone)]
+pub struct Backlink {
+    pub source: String,
+    pub link: String,
+}
+
+impl Backlink {
+    /// `.<author[is Post]`
+    pub fn expr(&self) -> String {
+        format!(".<{}[is {}]", self.link, self.source)
+    }
+
+    fn from_attr(attr: &syn::Attribute) -> darling::Result<Self> {
+        let error = || darling::Error::custom("expected #[backlink(Type.link)]").with_span(attr);
+
+        let syn::Expr::Field(field) = attr.parse_args::<syn::Expr>().map_err(|_| error())? else {
+            return Err(error());
+        };
+        let (syn::Expr::Path(source), syn::Member::Named(link)) = (*field.base, field.member)
+        else {
+            return Err(error());
+        };
+
+        Ok(Self {
+            source: source
+                .path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect::<Vec<_>>()
+                .join("::"),
+            link: link.to_string(),
+        })
+    }
 }
 
 #[derive(Debug, FromDeriveInput)]
-#[darling(forward_attrs(allow, doc, cfg, params, with, var, select, direct))]
+#[darling(forward_attrs(
+    allow, doc, cfg, params, with, var, select, direct, filter, order_by, offset, limit, group,
+    paginate, splat, edgedb, upsert
+))]
 pub struct ComposableQueryOpts {
     pub ident: syn::Ident,
     pub attrs: Vec<syn::Attribute>,
     pub data: ast::Data<util::Ignored, ComposableQueryReturn>,
 }
 
+/// `#[edgedb(type = "Inner", schema = "dbschema", index = ".req", returning = "InnerSelector")]`
+#[derive(Debug, Default)]
+pub struct EdgedbTypeAttr {
+    /// check the fields against this schema type
+    pub type_name: Option<String>,
+    /// the type an `EdgedbType` defines, not checked against the schema, which doesn't have it
+    /// yet
+    pub sdl_type: Option<String>,
+    /// where to find the `.esdl` files, relative to the crate
+    pub schema: Option<String>,
+    /// `index on (...)` for `EdgedbType`, can be repeated
+    pub indexes: Vec<String>,
+    /// the selector an `EdgedbInsert` returns, instead of the `id`
+    pub returning: Option<Type>,
+}
+
+/// `#[edgedb(required, multi, exclusive, default = "...", link = "...")]` on a field,
+/// for `EdgedbType` and `EdgedbInsert`
+#[derive(Debug, Default)]
+pub struct EdgedbFieldAttr {
+    pub required: bool,
+    pub multi: bool,
+    pub exclusive: bool,
+    pub default: Option<String>,
+    /// the target type of a `Uuid` or `Ref<T>` link
+    pub link: Option<String>,
+}
+
+impl EdgedbFieldAttr {
+    pub fn from_attrs(attrs: &[syn::Attribute]) -> darling::Result<Self> {
+        let mut res = Self::default();
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("edgedb")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("required") {
+                    res.required = true;
+                } else if meta.path.is_ident("multi") {
+                    res.multi = true;
+                } else if meta.path.is_ident("exclusive") {
+                    res.exclusive = true;
+                } else if meta.path.is_ident("default") {
+                    res.default = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("link") {
+                    res.link = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else {
+                    return Err(meta
+                        .error("expected `required`, `multi`, `exclusive`, `default` or `link`"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(res)
+    }
+}
+
+impl EdgedbTypeAttr {
+    pub fn from_attrs(attrs: &[syn::Attribute]) -> darling::Result<Self> {
+        let mut res = Self::default();
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("edgedb")) {
+            attr.parse_nested_meta(|meta| {
+                let value = meta.value()?.parse::<LitStr>()?;
+
+                if meta.path.is_ident("type") {
+                    res.type_name = Some(value.value());
+                } else if meta.path.is_ident("sdl_type") {
+                    res.sdl_type = Some(value.value());
+                } else if meta.path.is_ident("schema") {
+                    res.schema = Some(value.value());
+                } else if meta.path.is_ident("index") {
+                    res.indexes.push(value.value());
+                } else if meta.path.is_ident("returning") {
+                    res.returning = Some(value.parse::<Type>()?);
+                } else {
+                    return Err(
+                        meta.error("expected `type`, `sdl_type`, `schema`, `index` or `returning`")
+                    );
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(res)
+    }
+}
+
+/// `#[upsert(conflict_on = ".email")]` on an `EdgedbInsert`
+#[derive(Debug)]
+pub struct UpsertAttr {
+    /// the exclusive property or properties, `.email` or `(.a, .b)`
+    pub conflict_on: String,
+}
+
+impl UpsertAttr {
+    pub fn from_attrs(attrs: &[syn::Attribute]) -> darling::Result<Option<Self>> {
+        let mut res = None;
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("upsert")) {
+            if res.is_some() {
+                return Err(
+                    darling::Error::custom("expected only one upsert attribute").with_span(attr)
+                );
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("conflict_on") {
+                    res = Some(Self {
+                        conflict_on: meta.value()?.parse::<LitStr>()?.value(),
+                    });
+
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `conflict_on`"))
+                }
+            })?;
+        }
+
+        Ok(res)
+    }
+
+    /// The fields named in `conflict_on`, which aren't updated on a conflict
+    pub fn key_fields(&self) -> Vec<&str> {
+        self.conflict_on
+            .split('.')
+            .skip(1)
+            .map(|s| {
+                s.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
 impl FromField for ComposableQueryReturn {
     fn from_field(field: &syn::Field) -> darling::Result<Self> {
-        let _ident = field.ident.clone();
+        let ident = field.ident.clone();
 
         let ty = field.ty.clone();
 
@@ -48,11 +242,131 @@ impl FromField for ComposableQueryReturn {
 
         let field_name = field.ident.clone().map(|i| i.to_string());
 
+        let edgedb = EdgedbFieldAttr::from_attrs(&field.attrs)?;
+
+        let nested = nested_clauses(&field.attrs)?;
+
+        if var.is_some() && !nested.is_empty() {
+            return Err(darling::Error::custom(
+                "expected #[nested] on a nested selector, not on a #[var] field",
+            )
+            .with_span(&field));
+        }
+
+        let backlink = match field.attrs.iter().find(|a| a.path().is_ident("backlink")) {
+            Some(_) if var.is_some() => {
+                return Err(darling::Error::custom(
+                    "expected #[backlink] on a nested selector, not on a #[var] field",
+                )
+                .with_span(&field));
+            }
+            Some(attr) => Some(Backlink::from_attr(attr)?),
+            None => None,
+        };
+
+        let flatten = field.attrs.iter().any(|a| a.path().is_ident("flatten"));
+
+        if flatten && (var.is_some() || backlink.is_some() || !nested.is_empty()) {
+            return Err(darling::Error::custom(
+                "expected #[flatten] on a selector, without #[var], #[backlink] or #[nested]",
+            )
+            .with_span(&field));
+        }
+
+        let recursive = match field.attrs.iter().find(|a| a.path().is_ident("recursive")) {
+            Some(_) if var.is_some() || flatten => {
+                return Err(darling::Error::custom(
+                    "expected #[recursive] on a nested selector, not on a #[var] or #[flatten] field",
+                )
+                .with_span(&field));
+            }
+            Some(attr) => Some(recursive_depth(attr)?),
+            None => None,
+        };
+
+        let is = match field.attrs.iter().find(|a| a.path().is_ident("is")) {
+            Some(_) if var.is_some() || backlink.is_some() => {
+                return Err(darling::Error::custom(
+                    "expected #[is] on a property or a link, not on a #[var] or #[backlink] field",
+                )
+                .with_span(&field));
+            }
+            Some(attr) => Some(
+                attr.parse_args::<syn::Path>()?
+                    .segments
+                    .iter()
+                    .map(|s| s.ident.to_string())
+                    .collect::<Vec<_>>()
+                    .join("::"),
+            ),
+            None => None,
+        };
+
         Ok(Self {
+            ident,
             field_name,
-            // ident,
             ty,
             var,
+            edgedb,
+            nested,
+            backlink,
+            is,
+            flatten,
+            recursive,
         })
     }
 }
+
+/// `#[recursive(depth = 3)]`
+fn recursive_depth(attr: &syn::Attribute) -> darling::Result<usize> {
+    let mut depth = None;
+
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("depth") {
+            depth = Some(meta.value()?.parse::<syn::LitInt>()?.base10_parse()?);
+
+            Ok(())
+        } else {
+            Err(meta.error("expected `depth`"))
+        }
+    })?;
+
+    depth.ok_or_else(|| darling::Error::custom("expected #[recursive(depth = N)]").with_span(attr))
+}
+
+/// `#[nested(filter = ".published", order_by = ".created desc", limit = 5)]`: `filter` and
+/// `order_by` can be repeated, `offset` and `limit` are numbers or expressions
+fn nested_clauses(attrs: &[syn::Attribute]) -> darling::Result<Clauses> {
+    let mut clauses = Clauses::default();
+
+    for attr in attrs.iter().filter(|a| a.path().is_ident("nested")) {
+        attr.parse_nested_meta(|meta| {
+            let value = match meta.value()?.parse::<syn::Lit>()? {
+                syn::Lit::Str(s) => QueryVar::Var(s.value()),
+                syn::Lit::Int(n) => QueryVar::Var(n.base10_digits().to_string()),
+                lit => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "expected a string or a number",
+                    ))
+                }
+            };
+
+            if meta.path.is_ident("filter") {
+                clauses.filters.push(value);
+            } else if meta.path.is_ident("order_by") {
+                clauses.order_by.push(value);
+            } else if meta.path.is_ident("offset") {
+                clauses.offset = Some(value);
+            } else if meta.path.is_ident("limit") {
+                clauses.limit = Some(value);
+            } else {
+                return Err(meta.error("expected `filter`, `order_by`, `offset` or `limit`"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(clauses)
+}
@@ -21,6 +21,10 @@ pub enum QueryVar {
     // SomeSubQuery(k=v, k2=v2)
     // => (with ... select ...)
     Call(Path, HashMap<String, QueryVar>),
+
+    // file = "queries/something.edgeql"
+    // => absolute path, included at compile time
+    File(String),
 }
 
 // #[derive(Debug)]
@@ -29,11 +33,25 @@ pub enum QueryVar {
 //     InnerType(Type),
 // }
 
+/// `#[filter]`, `#[order_by]`, `#[offset]` and `#[limit]`, written after the shape
+#[derive(Debug, Clone, Default)]
+pub struct Clauses {
+    pub filters: Vec<QueryVar>,
+    pub order_by: Vec<QueryVar>,
+    pub offset: Option<QueryVar>,
+    pub limit: Option<QueryVar>,
+}
+
 #[derive(Debug)]
 pub struct Query {
     pub params: Params,
     pub withs: Vec<With>,
     pub result: QuerySelector,
+    pub clauses: Clauses,
+    /// `#[group(by = "...")]`, instead of a `select`
+    pub group: Option<QueryVar>,
+    /// `#[paginate(by = "...")]`, the field to order and page by
+    pub paginate: Option<syn::Ident>,
 }
 
 impl QueryVar {
@@ -80,9 +98,14 @@ impl ToTokens for QueryVar {
             QueryVar::Var(s) => tokens.append_all(quote! {
                 #s
             }),
+            QueryVar::File(path) => tokens.append_all(quote! {
+                ::edgedb_composable_query::__query_from_file(include_str!(#path))
+            }),
             QueryVar::Call(strct, args) => {
+                // sorted, for the same code on every build
                 let args_kv = args
                     .iter()
+                    .sorted_by_key(|(k, _)| *k)
                     .map(|(k, v)| {
                         quote! {
                             (#k, format!("({})", #v))
@@ -116,9 +139,63 @@ impl ToTokens for With {
     }
 }
 
-/// will be a function(fmt: &mut impl Write, args: &[&str])
-impl ToTokens for Query {
+impl Clauses {
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+            && self.order_by.is_empty()
+            && self.offset.is_none()
+            && self.limit.is_none()
+    }
+
+    /// code that writes the `filter`, without the pagination
+    pub fn filter_tokens(&self) -> TokenStream {
+        match self.filters.as_slice() {
+            [] => quote! {},
+            [filter] => quote! {
+                fmt.write_fmt(format_args!("\nfilter {}", #filter))?;
+            },
+            filters => quote! {
+                fmt.write_fmt(format_args!(
+                    "\nfilter {}",
+                    [#( format!("({})", #filters) ),*].join(" and ")
+                ))?;
+            },
+        }
+    }
+}
+
+/// will be code that writes to fmt
+impl ToTokens for Clauses {
     fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.append_all(self.filter_tokens());
+
+        let order_by = &self.order_by;
+        if !order_by.is_empty() {
+            tokens.append_all(quote! {
+                fmt.write_fmt(format_args!(
+                    "\norder by {}",
+                    [#( format!("{}", #order_by) ),*].join(" then ")
+                ))?;
+            });
+        }
+
+        if let Some(offset) = &self.offset {
+            tokens.append_all(quote! {
+                fmt.write_fmt(format_args!("\noffset {}", #offset))?;
+            });
+        }
+
+        if let Some(limit) = &self.limit {
+            tokens.append_all(quote! {
+                fmt.write_fmt(format_args!("\nlimit {}", #limit))?;
+            });
+        }
+    }
+}
+
+impl Query {
+    /// code that writes the `with` block of the params and `#[with]`s
+    fn with_tokens(&self) -> TokenStream {
         let mut inner = TokenStream::new();
 
         if !self.params.0.is_empty() || !self.withs.is_empty() {
@@ -133,6 +210,133 @@ impl ToTokens for Query {
             }
         }
 
+        inner
+    }
+
+    /// `format_aggregate` of `EdgedbSelectQuery`: `select count((select ... filter ...))`, with
+    /// the same `with`s. `None` for free objects and groups.
+    pub fn aggregate_tokens(&self) -> Option<TokenStream> {
+        let what = match &self.result {
+            _ if self.group.is_some() => return None,
+            QuerySelector::Object(..) | QuerySelector::Shape(_) => return None,
+            QuerySelector::Selector(what, _) => quote! { #what },
+            QuerySelector::Direct(what, _) => quote! { #what },
+        };
+
+        let inner = self.with_tokens();
+        let filter = self.clauses.filter_tokens();
+
+        Some(quote! {
+            fn format_aggregate(
+                fmt: &mut impl ::std::fmt::Write,
+                args: &::std::collections::HashMap<&str, String>,
+                function: &str
+            ) -> Result<(), ::std::fmt::Error> {
+                #inner
+
+                fmt.write_fmt(format_args!("select {}((select ({})", function, #what))?;
+
+                #filter
+
+                fmt.write_str("))")?;
+
+                Ok(())
+            }
+        })
+    }
+
+    /// `EdgedbPaginatedQuery`: the params with `_cursor` and `_limit` after them, and
+    /// `filter ... and .key > _cursor order by .key limit _limit + 1`. `None` without
+    /// `#[paginate]`.
+    pub fn page_tokens(&self) -> Option<TokenStream> {
+        let by = self.paginate.as_ref()?;
+        let QuerySelector::Direct(what, ty) = &self.result else {
+            return None;
+        };
+
+        let inner = self.with_tokens();
+        let key = format!(".{by}");
+        let filters = &self.clauses.filters;
+
+        let (argnames, argtypes) = self
+            .params
+            .0
+            .iter()
+            .cloned()
+            .unzip::<_, _, Vec<String>, Vec<Type>>();
+        let indices = (0..argnames.len()).map(syn::Index::from);
+
+        Some(quote! {
+            type Item = <#ty as ::std::iter::IntoIterator>::Item;
+            type PageArgs = (
+                #( #argtypes, )*
+                Option<::edgedb_composable_query::composable::Cursor>,
+                i64,
+            );
+
+            const PAGE_ARG_NAMES: &'static [&'static str] = &[#( #argnames, )* "_cursor", "_limit"];
+
+            fn page_args(
+                args: Self::ArgTypes,
+                after: Option<::edgedb_composable_query::composable::Cursor>,
+                limit: i64
+            ) -> Self::PageArgs {
+                let _ = &args;
+
+                (#( args.#indices, )* after, limit,)
+            }
+
+            fn cursor_of(
+                item: &Self::Item
+            ) -> ::edgedb_composable_query::Result<::edgedb_composable_query::composable::Cursor> {
+                ::edgedb_composable_query::composable::Cursor::of(item.#by.clone())
+            }
+
+            fn format_page(
+                fmt: &mut impl ::std::fmt::Write,
+                args: &::std::collections::HashMap<&str, String>
+            ) -> Result<(), ::std::fmt::Error> {
+                use ::edgedb_composable_query::composable::EdgedbComposableSelector;
+
+                let key_cast = ::edgedb_composable_query::composable::__page_key_cast(
+                    |item: &Self::Item| &item.#by
+                );
+
+                #inner
+
+                fmt.write_fmt(format_args!(
+                    "\t_cursor := <optional json>{},\n\t_limit := <int64>{},\n",
+                    args["_cursor"],
+                    args["_limit"]
+                ))?;
+
+                fmt.write_fmt(format_args!("select ({})", #what))?;
+                fmt.write_str(" {\n")?;
+
+                <#ty as EdgedbComposableSelector>::format_selector(fmt)?;
+
+                fmt.write_str("\n}")?;
+
+                fmt.write_fmt(format_args!(
+                    "\nfilter {}",
+                    [
+                        #( format!("({})", #filters), )*
+                        format!("(not exists _cursor or {} > <{}>_cursor)", #key, key_cast)
+                    ].join(" and ")
+                ))?;
+                fmt.write_fmt(format_args!("\norder by {}\nlimit _limit + 1", #key))?;
+
+                Ok(())
+            }
+        })
+    }
+}
+
+/// will be a function(fmt: &mut impl Write, args: &[&str])
+impl ToTokens for Query {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let inner = self.with_tokens();
+
         // self.result.to_tokens(&mut inner);
 
         let (argnames, argtypes) = self
@@ -142,14 +346,29 @@ impl ToTokens for Query {
             .cloned()
             .unzip::<_, _, Vec<String>, Vec<Type>>();
 
+        let clauses = &self.clauses;
+
+        let direct_format = match self.group {
+            Some(_) => "group ({})",
+            None => "select ({})",
+        };
+        let group_by = self.group.as_ref().map(|by| {
+            quote! {
+                fmt.write_fmt(format_args!("\nby {}", #by))?;
+            }
+        });
+
         let self_type = quote! {Self};
 
         let (final_selector, final_type) = match &self.result {
             QuerySelector::Selector(what, _) => (quote! {format!("select ({})", #what)}, self_type),
-            QuerySelector::Object(_) => (quote! {"select "}, self_type),
+            QuerySelector::Object(..) => (quote! {"select "}, self_type),
+            QuerySelector::Shape(_) => {
+                unreachable!("only selectors have a shape without #[select]")
+            }
             // QuerySelector::Tuple(_) => quote! {"select "},
             QuerySelector::Direct(what, _ty) => {
-                (quote! {format!("select ({})", #what)}, quote! {#_ty})
+                (quote! {format!(#direct_format, #what)}, quote! {#_ty})
             }
         };
 
@@ -181,6 +400,10 @@ impl ToTokens for Query {
 
                 fmt.write_str("\n}")?;
 
+                #group_by
+
+                #clauses
+
                 Ok(())
             }
         })
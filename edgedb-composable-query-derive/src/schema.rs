@@ -0,0 +1,284 @@
+use std::path::PathBuf;
+
+use darling::{ast, util, Error};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{GenericArgument, PathArguments, Type};
+
+use edgedb_composable_query_schema::esdl::{load_schema, ObjectType, Pointer, Schema};
+
+use crate::opts::ComposableQueryReturn;
+
+/// Overrides the default `dbschema` lookup, relative to the crate's manifest dir.
+pub const SCHEMA_DIR_ENV: &str = "EDGEDB_COMPOSABLE_SCHEMA_DIR";
+
+/// `#[edgedb(schema = "...")]`, then [`SCHEMA_DIR_ENV`], then the closest `dbschema`
+/// directory up from `CARGO_MANIFEST_DIR`
+pub fn schema_dir(attr: Option<&str>) -> Result<PathBuf, String> {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+
+    if let Some(dir) = attr
+        .map(str::to_owned)
+        .or_else(|| std::env::var(SCHEMA_DIR_ENV).ok())
+    {
+        return Ok(manifest_dir.join(dir));
+    }
+
+    manifest_dir
+        .ancestors()
+        .map(|d| d.join("dbschema"))
+        .find(|d| d.is_dir())
+        .ok_or_else(|| {
+            format!(
+                "couldn't find a dbschema directory above {}, set it with #[edgedb(schema = \"...\")] or {SCHEMA_DIR_ENV}",
+                manifest_dir.display()
+            )
+        })
+}
+
+/// How a field wraps its element type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wrapper {
+    None,
+    Option,
+    Vec,
+    NonEmpty,
+}
+
+fn unwrap_field_type(ty: &Type) -> (Wrapper, &Type) {
+    let Type::Path(path) = ty else {
+        return (Wrapper::None, ty);
+    };
+    let Some(last) = path.path.segments.last() else {
+        return (Wrapper::None, ty);
+    };
+
+    let wrapper = match last.ident.to_string().as_str() {
+        "Option" => Wrapper::Option,
+        "Vec" => Wrapper::Vec,
+        "NonEmpty" => Wrapper::NonEmpty,
+        _ => return (Wrapper::None, ty),
+    };
+
+    match &last.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(inner)) => (wrapper, inner),
+            _ => (Wrapper::None, ty),
+        },
+        _ => (Wrapper::None, ty),
+    }
+}
+
+/// The scalar a known Rust primitive decodes from (mirrors `EdgedbPrim::TYPE_CAST`)
+fn prim_scalar(ty: &Type) -> Option<&'static str> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    Some(
+        match path.path.segments.last()?.ident.to_string().as_str() {
+            "i16" => "int16",
+            "i32" => "int32",
+            "i64" => "int64",
+            "f32" => "float32",
+            "f64" => "float64",
+            "bool" => "bool",
+            "String" => "str",
+            "Uuid" => "uuid",
+            "Datetime" => "datetime",
+            _ => return None,
+        },
+    )
+}
+
+fn id_pointer() -> Pointer {
+    Pointer {
+        name: "id".to_string(),
+        target: "uuid".to_string(),
+        is_link: false,
+        required: true,
+        multi: false,
+        computed: false,
+    }
+}
+
+fn check_field(
+    schema: &Schema,
+    object: &ObjectType,
+    ident: &syn::Ident,
+    ty: &Type,
+) -> darling::Result<()> {
+    let name = ident.to_string();
+    let type_name = &object.name;
+
+    let pointer = match name.as_str() {
+        "id" => id_pointer(),
+        _ => schema.pointer(object, &name).cloned().ok_or_else(|| {
+            Error::custom(format!("`{type_name}` has no property or link `{name}`"))
+                .with_span(ident)
+        })?,
+    };
+
+    let kind = if pointer.is_link { "link" } else { "property" };
+    let (wrapper, inner) = unwrap_field_type(ty);
+
+    match wrapper {
+        Wrapper::Vec | Wrapper::NonEmpty if !pointer.multi => {
+            return Err(Error::custom(format!(
+                "`{name}` is a single {kind} of `{type_name}`, expected `Option<_>` or a plain type"
+            ))
+            .with_span(ty));
+        }
+        Wrapper::NonEmpty if !pointer.required && !pointer.computed => {
+            return Err(Error::custom(format!(
+                "`{name}` is not a required {kind} of `{type_name}`, it can be empty: use `Vec<_>`"
+            ))
+            .with_span(ty));
+        }
+        Wrapper::None | Wrapper::Option if pointer.multi => {
+            return Err(Error::custom(format!(
+                "`{name}` is a multi {kind} of `{type_name}`, expected `Vec<_>` or `NonEmpty<_>`"
+            ))
+            .with_span(ty));
+        }
+        Wrapper::None if !pointer.required && !pointer.computed => {
+            return Err(Error::custom(format!(
+                "`{name}` is an optional {kind} of `{type_name}`, expected `Option<_>`"
+            ))
+            .with_span(ty));
+        }
+        _ => {}
+    }
+
+    // computed pointers have no declared type to compare against
+    if pointer.computed {
+        return Ok(());
+    }
+
+    let Some(scalar) = prim_scalar(inner) else {
+        // a nested selector, or a custom `EdgedbPrim`
+        return Ok(());
+    };
+
+    if pointer.is_link {
+        return Err(Error::custom(format!(
+            "`{name}` is a link to `{}` in `{type_name}`, expected a selector, not a {scalar}",
+            pointer.target
+        ))
+        .with_span(inner));
+    }
+
+    match schema.base_scalar(&pointer.target) {
+        Some(base) if base != scalar => Err(Error::custom(format!(
+            "`{name}` is {} in `{type_name}`, but this type decodes from {scalar}",
+            match base == pointer.target {
+                true => base.to_string(),
+                false => format!("{} (based on {base})", pointer.target),
+            }
+        ))
+        .with_span(inner)),
+        _ => Ok(()),
+    }
+}
+
+/// Checks the selector's fields against `type_name` in the schema. Returns the tokens
+/// that make the crate rebuild when the schema changes.
+///
+/// A `#[splat]` selector may have `Option` fields the type doesn't: they're decoded as `None`.
+pub fn check_selector(
+    type_name: &str,
+    schema_attr: Option<&str>,
+    splat: bool,
+    data: &ast::Data<util::Ignored, ComposableQueryReturn>,
+) -> darling::Result<TokenStream> {
+    let loaded = schema_dir(schema_attr)
+        .and_then(|dir| load_schema(&dir))
+        .map_err(Error::custom)?;
+
+    let Some(object) = loaded.schema.object_type(type_name) else {
+        return Err(Error::custom(format!(
+            "no type `{type_name}` in the schema ({})",
+            loaded
+                .files
+                .iter()
+                .map(|f| f.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    };
+
+    let ast::Data::Struct(fields) = data else {
+        return Err(Error::custom("enums are not supported"));
+    };
+
+    let mut errors = Error::accumulator();
+
+    for field in fields.iter() {
+        let Some(ident) = &field.ident else {
+            return Err(Error::custom(
+                "#[edgedb(type = ...)] expects a struct with named fields",
+            ));
+        };
+
+        if field.var.is_some() || field.backlink.is_some() || field.flatten {
+            continue;
+        }
+
+        // `[is Subtype].field` is checked against the subtype
+        let object = match &field.is {
+            Some(is) => match loaded.schema.object_type(is) {
+                Some(subtype) => subtype,
+                None => {
+                    errors.push(Error::custom(format!("no type `{is}` in the schema")));
+                    continue;
+                }
+            },
+            None => object,
+        };
+
+        if splat
+            && matches!(unwrap_field_type(&field.ty).0, Wrapper::Option)
+            && ident != "id"
+            && loaded.schema.pointer(object, &ident.to_string()).is_none()
+        {
+            continue;
+        }
+
+        errors.handle(check_field(&loaded.schema, object, ident, &field.ty));
+    }
+
+    errors.finish()?;
+
+    let files = loaded
+        .files
+        .iter()
+        .map(|f| f.to_string_lossy().into_owned());
+
+    Ok(quote! {
+        #( const _: &str = include_str!(#files); )*
+    })
+}
+
+/// The schema and the tokens that make the crate rebuild when it changes, or `None` if
+/// there's no `dbschema` to be found. For derives where it's optional, unlike [`check_selector`].
+pub fn optional_schema(
+    schema_attr: Option<&str>,
+) -> darling::Result<Option<(Schema, TokenStream)>> {
+    let Ok(dir) = schema_dir(schema_attr) else {
+        return Ok(None);
+    };
+
+    let loaded = load_schema(&dir).map_err(Error::custom)?;
+
+    let files = loaded
+        .files
+        .iter()
+        .map(|f| f.to_string_lossy().into_owned());
+
+    Ok(Some((
+        loaded.schema,
+        quote! {
+            #( const _: &str = include_str!(#files); )*
+        },
+    )))
+}
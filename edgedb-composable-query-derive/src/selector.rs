@@ -1,20 +1,217 @@
+use itertools::Itertools;
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens, TokenStreamExt};
 use syn::Type;
 
-use crate::{opts::ComposableQueryReturn, query::QueryVar};
+use crate::{
+    opts::ComposableQueryReturn,
+    query::{Clauses, QueryVar},
+};
 
 #[derive(Debug)]
 pub enum SelectorValue {
-    SubSelector(Type),
+    /// a nested selector, with the `#[nested(...)]` clauses after its shape
+    SubSelector(Type, Box<Clauses>),
+    /// another path than `.name`: a backlink `.<link[is Source]`, or a type intersection
+    /// `[is Subtype].name`. Objects are `name := (select path { ... })`, with the
+    /// `#[nested(...)]` clauses, and properties `name := path`
+    Path(Type, String, Box<Clauses>),
+    /// `#[flatten]`: the fields of the selector, in this shape
+    Flatten(Type),
+    /// `#[recursive(depth = N)]`: the nested selector for `N` levels of this field, then left
+    /// out of the shape, or only its `CUTOFF`
+    Recursive(usize, Box<SelectorValue>),
+    /// `#[splat]`: `*`, or `**` with `#[splat(deep)]`
+    Splat(bool),
+    /// a field of a `#[splat]` selector: left out of the shape if it's a property, which the
+    /// splat selects, and selected as usual if it's a link
+    Splatted(Box<SelectorValue>),
     Computed(QueryVar),
 }
 
 impl From<&ComposableQueryReturn> for SelectorValue {
     fn from(cqr: &ComposableQueryReturn) -> Self {
-        match &cqr.var {
-            Some(v) => SelectorValue::Computed(v.clone()),
-            None => SelectorValue::SubSelector(cqr.ty.clone()),
+        let nested = Box::new(cqr.nested.clone());
+
+        if cqr.flatten {
+            return SelectorValue::Flatten(cqr.ty.clone());
+        }
+
+        let value = match (&cqr.var, &cqr.backlink, &cqr.is) {
+            (Some(v), _, _) => SelectorValue::Computed(v.clone()),
+            (None, Some(backlink), _) => {
+                SelectorValue::Path(cqr.ty.clone(), backlink.expr(), nested)
+            }
+            (None, None, Some(is)) => SelectorValue::Path(
+                cqr.ty.clone(),
+                format!(
+                    "[is {is}].{}",
+                    cqr.field_name.as_deref().unwrap_or_default()
+                ),
+                nested,
+            ),
+            (None, None, None) => SelectorValue::SubSelector(cqr.ty.clone(), nested),
+        };
+
+        match cqr.recursive {
+            Some(depth) => SelectorValue::Recursive(depth, Box::new(value)),
+            None => value,
+        }
+    }
+}
+
+impl SelectorValue {
+    /// In a `#[splat]` selector: the nested selectors without `#[nested(...)]` clauses are
+    /// maybe properties
+    pub fn splatted(self) -> Self {
+        match self {
+            SelectorValue::SubSelector(_, ref clauses) if clauses.is_empty() => {
+                SelectorValue::Splatted(Box::new(self))
+            }
+            value => value,
+        }
+    }
+
+    /// The same value, selected with another selector
+    fn with_type(&self, ty: Type) -> Self {
+        match self {
+            SelectorValue::SubSelector(_, clauses) => {
+                SelectorValue::SubSelector(ty, clauses.clone())
+            }
+            SelectorValue::Path(_, expr, clauses) => {
+                SelectorValue::Path(ty, expr.clone(), clauses.clone())
+            }
+            SelectorValue::Flatten(_) => SelectorValue::Flatten(ty),
+            SelectorValue::Recursive(depth, value) => {
+                SelectorValue::Recursive(*depth, Box::new(value.with_type(ty)))
+            }
+            SelectorValue::Splat(deep) => SelectorValue::Splat(*deep),
+            SelectorValue::Splatted(value) => {
+                SelectorValue::Splatted(Box::new(value.with_type(ty)))
+            }
+            SelectorValue::Computed(v) => SelectorValue::Computed(v.clone()),
+        }
+    }
+
+    /// code that returns what's after the name in the shape, or an `Option` of it for
+    /// `#[recursive]` fields
+    fn value_tokens(&self) -> TokenStream {
+        match self {
+            SelectorValue::SubSelector(ty, clauses) => {
+                let clauses = (!clauses.is_empty()).then(|| {
+                    quote! {{
+                        use ::std::fmt::Write as _;
+                        let fmt = &mut buf;
+
+                        #clauses
+                    }}
+                });
+
+                quote! {{
+                    let mut buf = String::new();
+                    <#ty as ::edgedb_composable_query::composable::EdgedbComposableSelector>::format_subquery(&mut buf)?;
+
+                    #clauses
+
+                    ::edgedb_composable_query::__query_add_indent(&buf)
+                }}
+            }
+            SelectorValue::Path(ty, expr, clauses) => {
+                let property = match clauses.is_empty() {
+                    true => quote! {
+                        fmt.write_fmt(format_args!(" := {}", #expr))?;
+                    },
+                    false => quote! {
+                        fmt.write_fmt(format_args!(" := (select {}", #expr))?;
+                        #clauses
+                        fmt.write_str(")")?;
+                    },
+                };
+
+                quote! {{
+                    use ::std::fmt::Write as _;
+                    use ::edgedb_composable_query::composable::{
+                        ComposableQueryResultKind, EdgedbComposableSelector,
+                    };
+
+                    let mut buf = String::new();
+                    let fmt = &mut buf;
+
+                    match <#ty as EdgedbComposableSelector>::RESULT_TYPE {
+                        ComposableQueryResultKind::Field => {
+                            #property
+                        }
+                        _ => {
+                            fmt.write_fmt(format_args!(" := (select {} {{\n", #expr))?;
+                            <#ty as EdgedbComposableSelector>::format_selector(fmt)?;
+                            fmt.write_str("\n}")?;
+
+                            #clauses
+
+                            fmt.write_str(")")?;
+                        }
+                    }
+
+                    ::edgedb_composable_query::__query_add_indent(&buf)
+                }}
+            }
+            SelectorValue::Flatten(ty) => quote! {{
+                let mut buf = String::new();
+                <#ty as ::edgedb_composable_query::composable::EdgedbComposableSelector>::format_selector(&mut buf)?;
+
+                ::edgedb_composable_query::__flatten_selector(&buf)
+            }},
+            SelectorValue::Recursive(depth, value) => {
+                let ty = match value.as_ref() {
+                    SelectorValue::SubSelector(ty, _) | SelectorValue::Path(ty, _, _) => ty,
+                    _ => unreachable!("#[recursive] is only on nested selectors"),
+                };
+                let cutoff = value
+                    .with_type(syn::parse_quote! {
+                        ::edgedb_composable_query::composable::__Cutoff<#ty>
+                    })
+                    .value_tokens();
+                let value = value.value_tokens();
+
+                quote! {{
+                    use ::edgedb_composable_query::composable::EdgedbComposableSelector;
+
+                    thread_local! {
+                        static LEVEL: ::std::cell::Cell<usize> = const { ::std::cell::Cell::new(0) };
+                    }
+
+                    const _: () = ::edgedb_composable_query::composable::__recursive_field_check::<#ty>();
+
+                    match ::edgedb_composable_query::composable::__recursive(
+                        &LEVEL,
+                        #depth,
+                        || -> Result<String, ::std::fmt::Error> { Ok(#value) },
+                    )? {
+                        Some(value) => Some(value),
+                        None if <#ty as EdgedbComposableSelector>::CUTOFF.is_some() => Some(#cutoff),
+                        None => None,
+                    }
+                }}
+            }
+            SelectorValue::Splat(deep) => {
+                let splat = if *deep { "**" } else { "*" };
+
+                quote! { String::from(#splat) }
+            }
+            SelectorValue::Splatted(value) => {
+                let SelectorValue::SubSelector(ty, _) = value.as_ref() else {
+                    unreachable!("only the nested selectors are splatted")
+                };
+                let value = value.value_tokens();
+
+                quote! {
+                    match <#ty as ::edgedb_composable_query::composable::EdgedbComposableSelector>::RESULT_TYPE {
+                        ::edgedb_composable_query::composable::ComposableQueryResultKind::Field => None,
+                        _ => Some(#value),
+                    }
+                }
+            }
+            SelectorValue::Computed(v) => quote! {format!(" := ({})", #v.to_string())},
         }
     }
 }
@@ -29,7 +226,11 @@ pub enum QuerySelector {
     /// default for named-structs: select fields from object. accepts [var(...)]
     /// as query return: `select {field := a, field2 := b}
     /// as subquery return: `select outerobj {thisfield := {field := a, field2 := b}}`
-    Object(Vec<(String, QueryVar)>),
+    /// and the `#[flatten]`ed selectors, after the fields
+    Object(Vec<(String, QueryVar)>, Vec<Type>),
+    /// a selector without [select(object)], with fields that need a shape, like `#[nested]`.
+    /// only as subquery return: `select outerobj {thisfield: {field, field2: ...}}`
+    Shape(Vec<(String, SelectorValue)>),
     /// todo: default for tuple-structs
     /// ?
     // Tuple(Vec<QueryVar>),
@@ -45,7 +246,7 @@ impl QuerySelector {
             QuerySelector::Selector(..) => {
                 quote! {::edgedb_composable_query::composable::ComposableQueryResultKind::Selector}
             }
-            QuerySelector::Object(..) => {
+            QuerySelector::Object(..) | QuerySelector::Shape(..) => {
                 quote! {::edgedb_composable_query::composable::ComposableQueryResultKind::Selector}
             }
             _ => {
@@ -59,28 +260,48 @@ impl QuerySelector {
 impl ToTokens for QuerySelector {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
-            QuerySelector::Selector(_fr, vals) => {
-                let (names, vars) = vals
+            QuerySelector::Selector(_, vals) | QuerySelector::Shape(vals) => {
+                let empty = String::new();
+                let names = vals
                     .iter()
                     .map(|(n, v)| match v {
-                        SelectorValue::SubSelector(ty) => {
-                            let ty = ty;
-                            // dbg!(ty);
-                            (
-                                n,
-                                quote! {{
-                                    let mut buf = String::new();
-                                    <#ty as ::edgedb_composable_query::composable::EdgedbComposableSelector>::format_subquery(&mut buf)?;
-
-                                    ::edgedb_composable_query::__query_add_indent(&buf)
-                                }},
-                            )
+                        SelectorValue::Flatten(_) | SelectorValue::Splat(_) => &empty,
+                        _ => n,
+                    })
+                    .collect_vec();
+
+                // a `#[recursive]` field is left out past its depth, and a splatted property
+                let optional = vals.iter().any(|(_, v)| {
+                    matches!(v, SelectorValue::Recursive(..) | SelectorValue::Splatted(_))
+                });
+
+                let vars = vals
+                    .iter()
+                    .map(|(_, v)| match v {
+                        SelectorValue::Recursive(..) | SelectorValue::Splatted(_) => {
+                            v.value_tokens()
                         }
-                        SelectorValue::Computed(v) => {
-                            (n, quote! {format!(" := ({})", #v.to_string())})
+                        _ if optional => {
+                            let value = v.value_tokens();
+                            quote! { Some(#value) }
                         }
+                        _ => v.value_tokens(),
                     })
-                    .unzip::<_, _, Vec<_>, Vec<_>>();
+                    .collect_vec();
+
+                if optional {
+                    tokens.append_all(quote! {
+                        fmt.write_fmt(format_args!(
+                            "\t{}",
+                            [#( (#names, #vars) ),*]
+                                .into_iter()
+                                .filter_map(|(n, v)| Some(format!("{}{}", n, v?)))
+                                .join(",\n\t")
+                        ))?;
+                    });
+
+                    return;
+                }
 
                 tokens.append_all(quote! {
                     fmt.write_fmt(
@@ -92,21 +313,45 @@ impl ToTokens for QuerySelector {
                     )?;
                 })
             }
-            QuerySelector::Object(mapping) => {
+            QuerySelector::Object(mapping, flattened) => {
                 let mapping_tuples = mapping.iter().map(|(k, v)| {
                     quote! {
                         (#k, #v)
                     }
                 });
 
+                let (names, vars): (Vec<_>, Vec<_>) = mapping.iter().cloned().unzip();
+
+                if flattened.is_empty() {
+                    tokens.append_all(quote! {
+                        fmt.write_fmt(format_args!(
+                            "{}",
+                            [#(#mapping_tuples),*]
+                                .iter()
+                                .map(|(k, v)| format!("\t{k} := ({v}),"))
+                                .join("\n")
+                        ))?;
+                    });
+
+                    return;
+                }
+
+                // the lines of the `#[flatten]`ed selectors, after the fields
                 tokens.append_all(quote! {
-                    fmt.write_fmt(format_args!(
-                        "{}",
-                        [#(#mapping_tuples),*]
-                            .iter()
-                            .map(|(k, v)| format!("\t{k} := ({v}),"))
-                            .join("\n")
-                    ))?;
+                    let mut lines: Vec<String> = vec![
+                        #( format!("\t{} := ({}),", #names, #vars), )*
+                    ];
+
+                    #(
+                        lines.push({
+                            let mut buf = String::new();
+                            <#flattened as ::edgedb_composable_query::composable::EdgedbComposableSelector>::format_selector(&mut buf)?;
+
+                            format!("\t{},", ::edgedb_composable_query::__flatten_selector(&buf))
+                        });
+                    )*
+
+                    fmt.write_str(&lines.join("\n"))?;
                 });
             }
             // QuerySelector::Tuple(vars) => {
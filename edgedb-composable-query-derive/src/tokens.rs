@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
 use darling::{ast, error::Accumulator, util, Error};
 use itertools::Itertools;
@@ -6,13 +6,13 @@ use itertools::Itertools;
 use strum_macros::EnumTryAs;
 use syn::{
     parse::Parse, punctuated::Punctuated, spanned::Spanned, token::Comma, Attribute, Expr, FnArg,
-    LitStr, MetaList, Pat, Type,
+    LitInt, LitStr, MetaList, MetaNameValue, Pat, Type,
 };
 
 use crate::{
     opts::ComposableQueryReturn,
-    query::{Params, Query, QueryVar, With},
-    selector::QuerySelector,
+    query::{Clauses, Params, Query, QueryVar, With},
+    selector::{QuerySelector, SelectorValue},
 };
 
 #[derive(Debug, EnumTryAs, Clone)]
@@ -22,7 +22,17 @@ pub enum ComposableQueryAttribute {
     // todo: #[select(something)] -> selector
     Select(QueryVar),
     // todo: #[direct(something)]
-    Direct(String),
+    Direct(QueryVar),
+    Filter(QueryVar),
+    OrderBy(QueryVar),
+    Offset(QueryVar),
+    Limit(QueryVar),
+    /// `#[group(by = ".status")]`
+    Group(QueryVar),
+    /// `#[paginate(by = "created_at")]`
+    Paginate(syn::Ident),
+    /// `#[splat]` or `#[splat(deep)]`: `*` or `**` instead of the properties
+    Splat(bool),
 }
 
 impl ComposableQueryAttribute {
@@ -89,6 +99,77 @@ impl ComposableQueryAttribute {
         )
         .cloned();
 
+        let clauses = Clauses {
+            filters: Self::by_discr(&attrs, ComposableQueryAttribute::try_as_filter_ref)
+                .cloned()
+                .collect(),
+            order_by: Self::by_discr(&attrs, ComposableQueryAttribute::try_as_order_by_ref)
+                .cloned()
+                .collect(),
+            offset: Self::by_discr_at_most_one(
+                &mut errors,
+                &attrs,
+                ComposableQueryAttribute::try_as_offset_ref,
+                "offset",
+            )
+            .cloned(),
+            limit: Self::by_discr_at_most_one(
+                &mut errors,
+                &attrs,
+                ComposableQueryAttribute::try_as_limit_ref,
+                "limit",
+            )
+            .cloned(),
+        };
+
+        let group = Self::by_discr_at_most_one(
+            &mut errors,
+            &attrs,
+            ComposableQueryAttribute::try_as_group_ref,
+            "group",
+        )
+        .cloned();
+
+        let paginate = Self::by_discr_at_most_one(
+            &mut errors,
+            &attrs,
+            ComposableQueryAttribute::try_as_paginate_ref,
+            "paginate",
+        )
+        .cloned();
+
+        // the page is ordered and limited by the key
+        if paginate.is_some()
+            && (!clauses.order_by.is_empty()
+                || clauses.offset.is_some()
+                || clauses.limit.is_some()
+                || group.is_some())
+        {
+            errors.push(Error::custom(
+                "expected no #[order_by], #[offset], #[limit] or #[group] with #[paginate]",
+            ));
+        }
+
+        // a grouped result can't be filtered or ordered without another select around it
+        if group.is_some()
+            && (!clauses.filters.is_empty()
+                || !clauses.order_by.is_empty()
+                || clauses.offset.is_some()
+                || clauses.limit.is_some())
+        {
+            errors.push(Error::custom(
+                "expected no #[filter], #[order_by], #[offset] or #[limit] with #[group]",
+            ));
+        }
+
+        let splat = Self::by_discr_at_most_one(
+            &mut errors,
+            &attrs,
+            ComposableQueryAttribute::try_as_splat_ref,
+            "splat",
+        )
+        .cloned();
+
         if direct.is_some() && selector.is_some() {
             errors.push(Error::custom(
                 "expected at most one of #[select] or #[direct]",
@@ -102,9 +183,9 @@ impl ComposableQueryAttribute {
 
             if fields.is_empty() {
                 match direct {
-                    Some(name) => {
+                    Some(direct) => {
                         return Ok(QuerySelector::Direct(
-                            QueryVar::Var(name.clone()),
+                            direct,
                             syn::parse2::<Type>(quote::quote! { () }).unwrap(),
                         ))
                     }
@@ -114,7 +195,25 @@ impl ComposableQueryAttribute {
                 }
             }
 
-            if fields.fields[0].field_name.is_none() {
+            let is_wrapper = fields.fields[0].field_name.is_none();
+
+            if group.is_some() && (!is_wrapper || direct.is_some()) {
+                return Err(
+                    "expected #[group] with #[select] on a wrapper struct, like `struct Q(Vec<Grouped<K, T>>)`",
+                );
+            }
+
+            if paginate.is_some() && (!is_wrapper || direct.is_some()) {
+                return Err(
+                    "expected #[paginate] with #[select] on a wrapper struct, like `struct Q(Vec<T>)`",
+                );
+            }
+
+            if splat.is_some() && (is_wrapper || direct.is_some()) {
+                return Err("expected #[splat] on a struct with named fields");
+            }
+
+            if is_wrapper {
                 if fields.fields.len() != 1 {
                     return Err("expected a single unnamed field (todo: tuples?)");
                 }
@@ -124,7 +223,6 @@ impl ComposableQueryAttribute {
                 */
 
                 let selector = direct
-                    .map(QueryVar::Var)
                     .or(selector)
                     .ok_or({ "expected #[select] or #[direct] attribute for wrapper structs" })?;
 
@@ -142,18 +240,26 @@ impl ComposableQueryAttribute {
                 return Err("expected no #[direct] attribute for non-empty structs");
             }
 
+            let vars_to_select = || {
+                let vars = fields.iter().map(|f| {
+                    (
+                        f.field_name
+                            .clone()
+                            .expect("We thought we have named fields here"),
+                        SelectorValue::from(f),
+                    )
+                });
+
+                match splat {
+                    Some(deep) => std::iter::once((String::new(), SelectorValue::Splat(deep)))
+                        .chain(vars.map(|(name, value)| (name, value.splatted())))
+                        .collect_vec(),
+                    None => vars.collect_vec(),
+                }
+            };
+
             if let Some(selector_from) = selector {
-                let vars_to_select = fields
-                    .iter()
-                    .map(|f| {
-                        (
-                            f.field_name
-                                .clone()
-                                .expect("We thought we have named fields here"),
-                            f.into(),
-                        )
-                    })
-                    .collect_vec();
+                let vars_to_select = vars_to_select();
 
                 if let Some(s) = selector_from.as_simple_name_or_ref() {
                     return Ok(QuerySelector::Selector(s.to_owned(), vars_to_select));
@@ -166,21 +272,51 @@ impl ComposableQueryAttribute {
                 return Ok(QuerySelector::Selector(name, vars_to_select));
             }
 
+            // a free object selects computeds, a splat and nested, backlink and recursive
+            // selectors need a shape: a selector has one without a #[select], it's the query
+            // that needs one
+            let needs_shape = if splat.is_some() {
+                Some("expected #[select] on a query with #[splat]")
+            } else if fields.iter().any(|f| !f.nested.is_empty()) {
+                Some("expected #[select] on a query with #[nested] fields")
+            } else if fields.iter().any(|f| f.backlink.is_some()) {
+                Some("expected #[select] on a query with #[backlink] fields")
+            } else if fields.iter().any(|f| f.recursive.is_some()) {
+                Some("expected #[select] on a query with #[recursive] fields")
+            } else {
+                None
+            };
+
+            if let Some(err) = needs_shape {
+                if selector_only {
+                    return Ok(QuerySelector::Shape(vars_to_select()));
+                }
+
+                return Err(err);
+            }
+
             Ok(QuerySelector::Object(
                 fields
                     .iter()
+                    .filter(|f| !f.flatten)
                     .map(|f| {
                         let fname = f
                             .field_name
                             .as_ref()
                             .cloned()
                             .expect("We thought we have named fields here");
-                        (
-                            fname.clone(),
-                            f.var.clone().unwrap_or(QueryVar::Var(format!(".{fname}"))),
-                        )
+                        let path = match &f.is {
+                            Some(is) => format!("[is {is}].{fname}"),
+                            None => format!(".{fname}"),
+                        };
+                        (fname.clone(), f.var.clone().unwrap_or(QueryVar::Var(path)))
                     })
                     .collect_vec(),
+                fields
+                    .iter()
+                    .filter(|f| f.flatten)
+                    .map(|f| f.ty.clone())
+                    .collect_vec(),
             ))
         })();
 
@@ -200,6 +336,9 @@ impl ComposableQueryAttribute {
             result,
             params,
             withs,
+            clauses,
+            group,
+            paginate,
         })
     }
 
@@ -263,27 +402,178 @@ impl ComposableQueryAttribute {
     }
 
     fn parse_selector(kind: &str, item: &MetaList) -> darling::Result<Self> {
+        let file = QueryVar::parse_file(item)?;
+
         match kind {
-            "select" => Ok(Self::Select(item.parse_args::<QueryVar>()?)),
-            "direct" => Ok(Self::Direct(item.parse_args::<LitStr>()?.value())),
+            "select" => Ok(Self::Select(match file {
+                Some(file) => file,
+                None => item.parse_args::<QueryVar>()?,
+            })),
+            "direct" => Ok(Self::Direct(match file {
+                Some(file) => file,
+                None => QueryVar::Var(item.parse_args::<LitStr>()?.value()),
+            })),
+            _ => unreachable!(),
+        }
+    }
+
+    /// `#[group(by = ".x")]`
+    fn parse_group(item: &MetaList) -> darling::Result<Self> {
+        let mut by = None;
+
+        item.parse_nested_meta(|arg| {
+            if !arg.path.is_ident("by") {
+                return Err(arg.error("expected `by`"));
+            }
+
+            by = Some(arg.value()?.parse::<QueryVar>()?);
+
+            Ok(())
+        })?;
+
+        let by = by.ok_or_else(|| darling::Error::custom("expected `by`").with_span(&item))?;
+
+        Ok(Self::Group(by))
+    }
+
+    /// `#[paginate(by = "x")]`, a field of the selector
+    fn parse_paginate(item: &MetaList) -> darling::Result<Self> {
+        let mut by = None;
+
+        item.parse_nested_meta(|arg| {
+            if !arg.path.is_ident("by") {
+                return Err(arg.error("expected `by`"));
+            }
+
+            by = Some(arg.value()?.parse::<LitStr>()?.parse::<syn::Ident>()?);
+
+            Ok(())
+        })?;
+
+        let by = by.ok_or_else(|| darling::Error::custom("expected `by`").with_span(&item))?;
+
+        Ok(Self::Paginate(by))
+    }
+
+    /// `#[splat]` or `#[splat(deep)]`
+    fn parse_splat(item: &syn::Meta) -> darling::Result<Self> {
+        let syn::Meta::List(list) = item else {
+            item.require_path_only()?;
+
+            return Ok(Self::Splat(false));
+        };
+
+        let mut deep = false;
+
+        list.parse_nested_meta(|arg| {
+            if !arg.path.is_ident("deep") {
+                return Err(arg.error("expected `deep`"));
+            }
+
+            deep = true;
+
+            Ok(())
+        })?;
+
+        Ok(Self::Splat(deep))
+    }
+
+    /// `#[filter(".x = x")]`, `#[order_by(".x desc")]`, `#[limit(n)]` or `#[limit(10)]`
+    fn parse_clause(kind: &str, item: &MetaList) -> darling::Result<Self> {
+        let value = match item.parse_args::<LitInt>() {
+            Ok(n) if matches!(kind, "offset" | "limit") => {
+                QueryVar::Var(n.base10_digits().to_string())
+            }
+            _ => item.parse_args::<QueryVar>()?,
+        };
+
+        match kind {
+            "filter" => Ok(Self::Filter(value)),
+            "order_by" => Ok(Self::OrderBy(value)),
+            "offset" => Ok(Self::Offset(value)),
+            "limit" => Ok(Self::Limit(value)),
             _ => unreachable!(),
         }
     }
 
     fn from_meta(item: &syn::Meta) -> darling::Result<Option<Self>> {
+        // doc comments, #[allow], #[edgedb(...)] are forwarded too
+        let Some(ident) = item.path().get_ident().map(|i| i.to_string()) else {
+            return Ok(None);
+        };
+
+        if !matches!(
+            &*ident,
+            "params"
+                | "with"
+                | "select"
+                | "direct"
+                | "filter"
+                | "order_by"
+                | "offset"
+                | "limit"
+                | "group"
+                | "paginate"
+                | "splat"
+        ) {
+            return Ok(None);
+        }
+
+        if ident == "splat" {
+            return Self::parse_splat(item).map(Some);
+        }
+
         let item = item.require_list()?;
-        let ident = item.path.require_ident()?.to_string();
 
         match &*ident {
             "params" => Self::parse_params(item).map(Some),
             "with" => Self::parse_with(item).map(Some),
             "select" | "direct" => Self::parse_selector(&ident, item).map(Some),
-            _ => Ok(None),
+            "filter" | "order_by" | "offset" | "limit" => {
+                Self::parse_clause(&ident, item).map(Some)
+            }
+            "group" => Self::parse_group(item).map(Some),
+            "paginate" => Self::parse_paginate(item).map(Some),
+            _ => unreachable!(),
         }
     }
 }
 
 impl QueryVar {
+    /// `file = "path/relative/to/the/crate.edgeql"`
+    fn parse_file(item: &MetaList) -> darling::Result<Option<Self>> {
+        let Ok(nv) = item.parse_args::<MetaNameValue>() else {
+            return Ok(None);
+        };
+
+        if !nv.path.is_ident("file") {
+            return Ok(None);
+        }
+
+        let Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(path),
+            ..
+        }) = &nv.value
+        else {
+            return Err(darling::Error::custom("expected a path string").with_span(&nv.value));
+        };
+
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+        let full_path = Path::new(&dir).join(path.value());
+
+        if !full_path.is_file() {
+            return Err(darling::Error::custom(format!(
+                "couldn't find query file {}",
+                full_path.display()
+            ))
+            .with_span(path));
+        }
+
+        Ok(Some(QueryVar::File(
+            full_path.to_string_lossy().into_owned(),
+        )))
+    }
+
     fn from_expr(expr: syn::Expr) -> syn::Result<Self> {
         let span = expr.span();
         match expr {
@@ -0,0 +1,227 @@
+use darling::FromDeriveInput;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Type};
+
+use crate::{
+    insert::{Mutation, MutationField},
+    opts::ComposableQueryOpts,
+};
+
+/// `Option<_>` or `Patch<_>`: a filter that's skipped when it's `None` or `Keep`
+fn is_optional(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+
+    matches!(
+        path.path
+            .segments
+            .last()
+            .map(|s| s.ident.to_string())
+            .as_deref(),
+        Some("Option" | "Patch")
+    )
+}
+
+/// `count(...)`, or `(...) { shape }` of the `returning` selector
+fn wrap_returning(mutation: &Mutation, body: TokenStream) -> (TokenStream, TokenStream) {
+    match mutation.returning_shape() {
+        Some((return_type, shape)) => (
+            return_type,
+            quote! {
+                fmt.write_str("select (")?;
+                #body
+                fmt.write_str(")")?;
+                #shape
+            },
+        ),
+        None => (
+            quote! { i64 },
+            quote! {
+                fmt.write_str("select count((")?;
+                #body
+                fmt.write_str("))")?;
+            },
+        ),
+    }
+}
+
+pub fn derive_update_impl(item: DeriveInput) -> darling::Result<proc_macro2::TokenStream> {
+    let item = ComposableQueryOpts::from_derive_input(&item)?;
+    let mutation = Mutation::from_opts(&item, "update")?;
+    let type_name = &mutation.type_name;
+
+    let Some(id) = mutation.fields.iter().find(|f| f.name == "id") else {
+        return Err(
+            darling::Error::custom("expected an `id` field to filter by").with_span(mutation.ident),
+        );
+    };
+    let id_ty = id.ty;
+
+    let assignments = mutation.fields.iter().filter(|f| f.name != "id").map(|f| {
+        let MutationField { name, ty, .. } = f;
+        let link = f.link_tokens();
+
+        quote! {
+            fmt.write_fmt(format_args!(
+                "\t{},\n",
+                ::edgedb_composable_query::composable::__update_set::<#ty>(#name, #link, &args[#name])
+            ))?;
+        }
+    });
+
+    let (return_type, body) = wrap_returning(
+        &mutation,
+        quote! {
+            fmt.write_fmt(format_args!(
+                "update {} filter .id = {} set {{\n",
+                #type_name,
+                ::edgedb_composable_query::__query_arg::<#id_ty>(&args["id"])
+            ))?;
+
+            #( #assignments )*
+
+            fmt.write_str("}")?;
+        },
+    );
+
+    Ok(mutation.impls(quote! { EdgedbUpdate }, quote! {}, return_type, body))
+}
+
+pub fn derive_delete_impl(item: DeriveInput) -> darling::Result<proc_macro2::TokenStream> {
+    let item = ComposableQueryOpts::from_derive_input(&item)?;
+    let mutation = Mutation::from_opts(&item, "delete")?;
+    let type_name = &mutation.type_name;
+
+    // deleting everything is easier to do without a derive
+    if mutation.fields.is_empty() {
+        return Err(
+            darling::Error::custom("expected at least one field to filter by")
+                .with_span(mutation.ident),
+        );
+    }
+
+    // and with only optional ones, all of them `None` would delete everything too
+    if mutation.fields.iter().all(|f| is_optional(f.ty)) {
+        return Err(darling::Error::custom(
+            "expected at least one field to filter by that isn't an `Option` or a `Patch`",
+        )
+        .with_span(mutation.ident));
+    }
+
+    let filters = mutation.fields.iter().map(|f| {
+        let MutationField { name, ty, .. } = f;
+        let link = f.link_tokens();
+
+        quote! {
+            ::edgedb_composable_query::composable::__delete_filter::<#ty>(#name, #link, &args[#name])
+        }
+    });
+
+    let (return_type, body) = wrap_returning(
+        &mutation,
+        quote! {
+            fmt.write_fmt(format_args!(
+                "delete {} filter {}",
+                #type_name,
+                [#( #filters ),*].join(" and ")
+            ))?;
+        },
+    );
+
+    Ok(mutation.impls(quote! { EdgedbDelete }, quote! {}, return_type, body))
+}
+
+#[cfg(test)]
+mod test {
+    use quote::quote;
+    use syn::DeriveInput;
+
+    use super::{derive_delete_impl, derive_update_impl};
+
+    fn pretty(out: proc_macro2::TokenStream) -> String {
+        // without the `include_str!`s of the local dbschema
+        prettyplease::unparse(&syn::parse_file(&out.to_string()).unwrap())
+            .lines()
+            .filter(|l| !l.starts_with("const _: &str = include_str!"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn insta_test_update() {
+        let input = quote! {
+
+            #[derive(EdgedbUpdate)]
+            #[edgedb(type = "Outer", returning = "Option<OuterSelector>")]
+            struct UpdateOuter {
+                id: Uuid,
+                other_field: Option<String>,
+                some_field: Patch<Option<String>>,
+                inner: Patch<Option<Ref<Inner>>>,
+            }
+
+        };
+
+        let out = derive_update_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap();
+
+        insta::assert_snapshot!(pretty(out));
+    }
+
+    #[test]
+    fn insta_test_delete() {
+        let input = quote! {
+
+            #[derive(EdgedbDelete)]
+            #[edgedb(type = "Inner")]
+            struct DeleteInners {
+                req: String,
+                opt: Option<String>,
+            }
+
+        };
+
+        let out = derive_delete_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap();
+
+        insta::assert_snapshot!(pretty(out));
+    }
+
+    #[test]
+    fn update_without_id() {
+        let input = quote! {
+
+            #[derive(EdgedbUpdate)]
+            #[edgedb(type = "Inner")]
+            struct UpdateInner {
+                req: String,
+            }
+
+        };
+
+        let err = derive_update_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap_err();
+
+        assert_eq!(err.to_string(), "expected an `id` field to filter by");
+    }
+
+    #[test]
+    fn delete_only_optional_filters() {
+        let input = quote! {
+
+            #[derive(EdgedbDelete)]
+            #[edgedb(type = "Outer")]
+            struct DeleteOuters {
+                inner: Option<Ref<Inner>>,
+                some_field: Patch<Option<String>>,
+            }
+
+        };
+
+        let err = derive_delete_impl(syn::parse2::<DeriveInput>(input).unwrap()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "expected at least one field to filter by that isn't an `Option` or a `Patch`"
+        );
+    }
+}
@@ -0,0 +1,761 @@
+//! A tolerant parser for the parts of EdgeDB SDL (`dbschema/*.esdl`) the derives and the
+//! generator care about: object types with their properties and links, and scalar types.
+//! Everything else (functions, aliases, globals, constraints, indexes...) is skipped.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pointer {
+    pub name: String,
+    /// module-qualified, unless it's in `default` or `std`
+    pub target: String,
+    pub is_link: bool,
+    pub required: bool,
+    pub multi: bool,
+    pub computed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectType {
+    pub name: String,
+    pub is_abstract: bool,
+    pub extending: Vec<String>,
+    pub pointers: Vec<Pointer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScalarType {
+    pub name: String,
+    pub extending: Vec<String>,
+    pub enum_values: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Schema {
+    pub object_types: HashMap<String, ObjectType>,
+    pub scalar_types: HashMap<String, ScalarType>,
+}
+
+/// The parsed `*.esdl` files of a schema directory.
+pub struct LoadedSchema {
+    pub schema: Schema,
+    pub files: Vec<PathBuf>,
+}
+
+/// Parses all the `*.esdl` files in `dir`
+pub fn load_schema(dir: &Path) -> Result<LoadedSchema, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("couldn't read schema directory {}: {e}", dir.display()))?;
+
+    let mut files = entries
+        .filter_map(|e| Some(e.ok()?.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "esdl"))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    if files.is_empty() {
+        return Err(format!("no .esdl files in {}", dir.display()));
+    }
+
+    let mut schema = Schema::default();
+
+    for file in &files {
+        let src = std::fs::read_to_string(file)
+            .map_err(|e| format!("couldn't read {}: {e}", file.display()))?;
+
+        schema
+            .parse_into(&src)
+            .map_err(|e| format!("couldn't parse {}: {e}", file.display()))?;
+    }
+
+    Ok(LoadedSchema { schema, files })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Punct(char),
+    /// `->` and `:=`
+    Op(&'static str),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = src.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '#' {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => s.extend(chars.next()),
+                    Some(q) if q == c => break,
+                    Some(ch) => s.push(ch),
+                    None => return Err("unterminated string literal".to_string()),
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else if c == '`' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('`') => break,
+                    Some(ch) => s.push(ch),
+                    None => return Err("unterminated quoted identifier".to_string()),
+                }
+            }
+            tokens.push(Token::Ident(s));
+        } else if c.is_alphanumeric() || c == '_' {
+            let mut s = String::new();
+            while let Some(&ch) = chars.peek() {
+                if ch.is_alphanumeric() || ch == '_' {
+                    s.push(ch);
+                    chars.next();
+                } else if ch == ':' {
+                    // `module::Name`
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    if ahead.peek() == Some(&':') {
+                        s.push_str("::");
+                        chars.next();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Ident(s));
+        } else {
+            chars.next();
+            match (c, chars.peek()) {
+                ('-', Some('>')) => {
+                    chars.next();
+                    tokens.push(Token::Op("->"));
+                }
+                (':', Some('=')) => {
+                    chars.next();
+                    tokens.push(Token::Op(":="));
+                }
+                _ => tokens.push(Token::Punct(c)),
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+const POINTER_QUALIFIERS: &[&str] = &[
+    "required",
+    "optional",
+    "single",
+    "multi",
+    "overloaded",
+    "readonly",
+];
+
+const TYPE_BODY_SKIPPED: &[&str] = &[
+    "constraint",
+    "index",
+    "deferred",
+    "annotation",
+    "access",
+    "trigger",
+    "rewrite",
+    "delegated",
+];
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_ident(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Ident(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_ident(&mut self, s: &str) -> bool {
+        if self.peek_ident() == Some(s) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Ident(s)) => Ok(s),
+            t => Err(format!("expected a name, got {t:?}")),
+        }
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<(), String> {
+        if self.eat_punct(c) {
+            Ok(())
+        } else {
+            Err(format!("expected `{c}`, got {:?}", self.peek()))
+        }
+    }
+
+    /// skips a `{ ... }` block, if the next token starts one
+    fn skip_block(&mut self) -> Result<(), String> {
+        if !self.eat_punct('{') {
+            return Ok(());
+        }
+
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Punct('{')) => depth += 1,
+                Some(Token::Punct('}')) => depth -= 1,
+                Some(_) => {}
+                None => return Err("unbalanced `{`".to_string()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// skips to the `;` ending the current declaration, or to the `}` of the enclosing block
+    fn skip_declaration(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+
+        loop {
+            match self.peek() {
+                None => return Ok(()),
+                Some(Token::Punct('{' | '(' | '[')) => depth += 1,
+                Some(Token::Punct('}' | ')' | ']')) if depth == 0 => return Ok(()),
+                Some(Token::Punct('}')) if depth == 1 => {
+                    // blocks don't need a `;` after them
+                    self.pos += 1;
+                    self.eat_punct(';');
+                    return Ok(());
+                }
+                Some(Token::Punct('}' | ')' | ']')) => depth -= 1,
+                Some(Token::Punct(';')) if depth == 0 => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// `A, B, C` up to `{` or `;`
+    fn type_list(&mut self, module: &str) -> Result<Vec<String>, String> {
+        let mut names = vec![qualify(module, &self.type_expr()?)];
+
+        while self.eat_punct(',') {
+            names.push(qualify(module, &self.type_expr()?));
+        }
+
+        Ok(names)
+    }
+
+    /// a type expression, e.g. `str`, `array<str>`, `default::User`, `A | B`
+    fn type_expr(&mut self) -> Result<String, String> {
+        let mut s = String::new();
+        let mut depth = 0;
+
+        loop {
+            match self.peek() {
+                Some(Token::Ident(i)) => {
+                    if !s.is_empty() && !s.ends_with(['<', ' ', ',']) {
+                        break;
+                    }
+                    s.push_str(i);
+                }
+                Some(Token::Punct('<')) => {
+                    depth += 1;
+                    s.push('<');
+                }
+                Some(Token::Punct('>')) if depth > 0 => {
+                    depth -= 1;
+                    s.push('>');
+                }
+                Some(Token::Punct(',')) if depth > 0 => s.push_str(", "),
+                Some(Token::Punct('|')) if depth == 0 => s.push_str(" | "),
+                Some(Token::Str(v)) if depth > 0 => s.push_str(&format!("'{v}'")),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        if s.is_empty() {
+            return Err(format!("expected a type, got {:?}", self.peek()));
+        }
+
+        Ok(s)
+    }
+
+    fn parse_schema(&mut self, schema: &mut Schema) -> Result<(), String> {
+        while self.peek().is_some() {
+            if self.eat_ident("module") {
+                let module = self.expect_ident()?;
+                self.expect_punct('{')?;
+                self.parse_module(&module, schema)?;
+                self.expect_punct('}')?;
+                self.eat_punct(';');
+            } else if self.eat_punct(';') {
+            } else {
+                self.skip_declaration()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_module(&mut self, module: &str, schema: &mut Schema) -> Result<(), String> {
+        loop {
+            match self.peek() {
+                None | Some(Token::Punct('}')) => return Ok(()),
+                Some(Token::Punct(';')) => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            if self.eat_ident("module") {
+                let inner = format!("{module}::{}", self.expect_ident()?);
+                self.expect_punct('{')?;
+                self.parse_module(&inner, schema)?;
+                self.expect_punct('}')?;
+                continue;
+            }
+
+            let start = self.pos;
+            let is_abstract = self.eat_ident("abstract");
+
+            if self.eat_ident("type") {
+                let ty = self.parse_object_type(module, is_abstract)?;
+                schema.object_types.insert(ty.name.clone(), ty);
+            } else if self.eat_ident("scalar") && self.eat_ident("type") {
+                let ty = self.parse_scalar_type(module)?;
+                schema.scalar_types.insert(ty.name.clone(), ty);
+            } else {
+                self.pos = start;
+                self.skip_declaration()?;
+            }
+        }
+    }
+
+    fn parse_object_type(&mut self, module: &str, is_abstract: bool) -> Result<ObjectType, String> {
+        let name = qualify(module, &self.expect_ident()?);
+
+        let extending = if self.eat_ident("extending") {
+            self.type_list(module)?
+        } else {
+            vec![]
+        };
+
+        let mut pointers = vec![];
+
+        if self.eat_punct('{') {
+            loop {
+                if self.eat_punct('}') {
+                    break;
+                }
+                if self.eat_punct(';') {
+                    continue;
+                }
+                if self.peek().is_none() {
+                    return Err(format!("unterminated type {name}"));
+                }
+
+                match self.parse_pointer(module)? {
+                    Some(p) => pointers.push(p),
+                    None => self.skip_declaration()?,
+                }
+            }
+        }
+
+        self.eat_punct(';');
+
+        Ok(ObjectType {
+            name,
+            is_abstract,
+            extending,
+            pointers,
+        })
+    }
+
+    /// `None` (and nothing consumed) if it's not a pointer declaration
+    fn parse_pointer(&mut self, module: &str) -> Result<Option<Pointer>, String> {
+        let start = self.pos;
+
+        if self
+            .peek_ident()
+            .is_some_and(|i| TYPE_BODY_SKIPPED.contains(&i))
+        {
+            return Ok(None);
+        }
+
+        let mut required = false;
+        let mut multi = false;
+
+        while let Some(q) = self.peek_ident() {
+            if !POINTER_QUALIFIERS.contains(&q) {
+                break;
+            }
+            match q {
+                "required" => required = true,
+                "multi" => multi = true,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+
+        let mut is_link = None;
+        if self.eat_ident("link") {
+            is_link = Some(true);
+        } else if self.eat_ident("property") {
+            is_link = Some(false);
+        }
+
+        let Some(name) = self.peek_ident().map(str::to_owned) else {
+            self.pos = start;
+            return Ok(None);
+        };
+        self.pos += 1;
+
+        let (target, computed) = match self.next() {
+            Some(Token::Punct(':') | Token::Op("->")) => {
+                (qualify(module, &self.type_expr()?), false)
+            }
+            Some(Token::Op(":=")) => {
+                self.pos -= 1;
+                self.skip_declaration()?;
+                return Ok(Some(Pointer {
+                    name,
+                    target: String::new(),
+                    is_link: is_link.unwrap_or(false),
+                    required,
+                    multi,
+                    computed: true,
+                }));
+            }
+            _ => {
+                self.pos = start;
+                return Ok(None);
+            }
+        };
+
+        self.skip_block()?;
+        self.eat_punct(';');
+
+        Ok(Some(Pointer {
+            name,
+            is_link: is_link.unwrap_or_else(|| !is_scalar_like(&target)),
+            target,
+            required,
+            multi,
+            computed,
+        }))
+    }
+
+    fn parse_scalar_type(&mut self, module: &str) -> Result<ScalarType, String> {
+        let name = qualify(module, &self.expect_ident()?);
+
+        let mut extending = vec![];
+        let mut enum_values = None;
+
+        if self.eat_ident("extending") {
+            loop {
+                if self.eat_ident("enum") {
+                    self.expect_punct('<')?;
+                    let mut values = vec![];
+                    loop {
+                        match self.next() {
+                            Some(Token::Ident(v) | Token::Str(v)) => values.push(v),
+                            Some(Token::Punct(',')) => {}
+                            Some(Token::Punct('>')) => break,
+                            t => return Err(format!("unexpected {t:?} in enum of {name}")),
+                        }
+                    }
+                    enum_values = Some(values);
+                } else {
+                    extending.push(qualify(module, &self.type_expr()?));
+                }
+
+                if !self.eat_punct(',') {
+                    break;
+                }
+            }
+        }
+
+        self.skip_block()?;
+        self.eat_punct(';');
+
+        Ok(ScalarType {
+            name,
+            extending,
+            enum_values,
+        })
+    }
+}
+
+/// scalars and collections are properties, everything else is a link
+fn is_scalar_like(target: &str) -> bool {
+    target.starts_with("array<")
+        || target.starts_with("tuple<")
+        || target.starts_with("range<")
+        || target.starts_with("multirange<")
+        || STD_SCALARS.contains(&target)
+        || target.starts_with("cal::")
+        || target.starts_with("cfg::")
+        || target.starts_with("ext::")
+}
+
+pub const STD_SCALARS: &[&str] = &[
+    "str", "bool", "int16", "int32", "int64", "float32", "float64", "bigint", "decimal", "uuid",
+    "json", "bytes", "datetime", "duration", "sequence",
+];
+
+/// `name` as it appears in `module`, with `default::` and `std::` dropped
+pub fn qualify(module: &str, name: &str) -> String {
+    let name = name.strip_prefix("std::").unwrap_or(name);
+
+    if let Some(name) = name.strip_prefix("default::") {
+        return name.to_string();
+    }
+
+    if name.contains("::")
+        || name.contains('<')
+        || module == "default"
+        || STD_SCALARS.contains(&name)
+    {
+        name.to_string()
+    } else {
+        format!("{module}::{name}")
+    }
+}
+
+impl Schema {
+    /// Adds the declarations from one `.esdl` file
+    pub fn parse_into(&mut self, src: &str) -> Result<(), String> {
+        Parser {
+            tokens: tokenize(src)?,
+            pos: 0,
+        }
+        .parse_schema(self)?;
+
+        // `prop: SomeScalar` can't be told from a link until the scalar is declared,
+        // possibly later, or in another file
+        for ty in self.object_types.values_mut() {
+            for p in &mut ty.pointers {
+                if self.scalar_types.contains_key(&p.target) {
+                    p.is_link = false;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `name` can omit `default::`
+    pub fn object_type(&self, name: &str) -> Option<&ObjectType> {
+        self.object_types.get(&qualify("default", name))
+    }
+
+    /// Own and inherited pointers, except `id`
+    pub fn pointer<'a>(&'a self, ty: &'a ObjectType, name: &str) -> Option<&'a Pointer> {
+        ty.pointers.iter().find(|p| p.name == name).or_else(|| {
+            ty.extending
+                .iter()
+                .filter_map(|parent| self.object_types.get(parent))
+                .find_map(|parent| self.pointer(parent, name))
+        })
+    }
+
+    /// The `std` scalar a scalar type is eventually based on
+    pub fn base_scalar<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        if STD_SCALARS.contains(&name) {
+            return Some(name);
+        }
+
+        let scalar = self.scalar_types.get(name)?;
+
+        if scalar.enum_values.is_some() {
+            return None;
+        }
+
+        scalar
+            .extending
+            .iter()
+            .find_map(|parent| self.base_scalar(parent))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Pointer, Schema};
+
+    fn parse(src: &str) -> Schema {
+        let mut schema = Schema::default();
+        schema.parse_into(src).unwrap();
+        schema
+    }
+
+    const SCHEMA: &str = r#"
+        # comment
+        module default {
+            scalar type Status extending enum<Active, 'Not Active'>;
+            scalar type Slug extending str {
+                constraint regexp(r'^[a-z-]+$');
+            };
+
+            abstract type Audited {
+                created_at: datetime {
+                    default := datetime_current();
+                    readonly := true;
+                };
+            }
+
+            type Inner extending Audited {
+                required req: str;
+                opt: str;
+                status: Status;
+                multi tags: array<str>;
+                index on (.req);
+                constraint exclusive on ((.req, .opt));
+            }
+
+            type Outer {
+                inner: Inner;
+                required other_field: str;
+                some_field: str;
+                multi link items -> Inner {
+                    property weight -> int64;
+                };
+                required property slug -> Slug;
+                req_len := len(.other_field);
+            };
+
+            function hello(name: str) -> str using ('hello ' ++ name);
+            alias InnerAlias := Inner { extra := 1 };
+        }
+
+        module other {
+            type Thing {
+                required name: str;
+                owner: default::Outer;
+                sibling: Thing;
+            }
+        }
+    "#;
+
+    #[test]
+    fn parses_types_and_pointers() {
+        let schema = parse(SCHEMA);
+
+        let mut names = schema.object_types.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["Audited", "Inner", "Outer", "other::Thing"]);
+
+        let inner = schema.object_type("default::Inner").unwrap();
+        assert_eq!(inner.extending, ["Audited"]);
+        assert_eq!(
+            inner
+                .pointers
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            ["req", "opt", "status", "tags"]
+        );
+        assert_eq!(
+            schema.pointer(inner, "created_at").unwrap().target,
+            "datetime"
+        );
+
+        let outer = schema.object_type("Outer").unwrap();
+        assert_eq!(
+            schema.pointer(outer, "items").unwrap(),
+            &Pointer {
+                name: "items".to_string(),
+                target: "Inner".to_string(),
+                is_link: true,
+                required: false,
+                multi: true,
+                computed: false,
+            }
+        );
+        assert!(schema.pointer(outer, "inner").unwrap().is_link);
+        assert!(schema.pointer(outer, "slug").unwrap().required);
+        assert!(!schema.pointer(outer, "slug").unwrap().is_link);
+        assert!(schema.pointer(outer, "req_len").unwrap().computed);
+        assert!(schema.pointer(outer, "nope").is_none());
+
+        let thing = schema.object_type("other::Thing").unwrap();
+        assert_eq!(schema.pointer(thing, "owner").unwrap().target, "Outer");
+        assert_eq!(
+            schema.pointer(thing, "sibling").unwrap().target,
+            "other::Thing"
+        );
+    }
+
+    #[test]
+    fn parses_scalars() {
+        let schema = parse(SCHEMA);
+
+        assert_eq!(
+            schema.scalar_types["Status"].enum_values,
+            Some(vec!["Active".to_string(), "Not Active".to_string()])
+        );
+        assert_eq!(schema.base_scalar("Slug"), Some("str"));
+        assert_eq!(schema.base_scalar("Status"), None);
+        assert_eq!(schema.base_scalar("int64"), Some("int64"));
+
+        let inner = schema.object_type("Inner").unwrap();
+        assert!(!schema.pointer(inner, "status").unwrap().is_link);
+    }
+
+    #[test]
+    fn parses_repo_schema() {
+        let schema = parse(include_str!("../../dbschema/default.esdl"));
+
+        let outer = schema.object_type("Outer").unwrap();
+        assert_eq!(outer.pointers.len(), 5);
+        assert!(schema.pointer(outer, "other_field").unwrap().required);
+    }
+}
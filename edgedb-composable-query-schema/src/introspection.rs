@@ -0,0 +1,177 @@
+//! The same [`Schema`], read from the server's introspection instead of the `.esdl` files.
+
+use serde::Deserialize;
+
+use crate::esdl::{qualify, ObjectType, Pointer, ScalarType, Schema};
+
+/// Dumps the user-defined types as JSON for [`schema_from_json`]:
+///
+/// ```sh
+/// edgedb query --output-format json "$(cat introspection.edgeql)" > schema.json
+/// ```
+pub const INTROSPECTION_QUERY: &str = r#"select {
+    object_types := (
+        select schema::ObjectType {
+            name,
+            abstract,
+            bases: { name },
+            pointers: {
+                name,
+                required,
+                cardinality,
+                target: { name },
+                is_link := .__type__.name = 'schema::Link',
+                computed := exists .expr,
+            },
+        }
+        filter not .builtin and not .from_alias
+    ),
+    scalar_types := (
+        select schema::ScalarType {
+            name,
+            enum_values,
+            bases: { name },
+        }
+        filter not .builtin
+    ),
+}"#;
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntrospectedPointer {
+    name: String,
+    required: bool,
+    cardinality: String,
+    target: Option<Named>,
+    is_link: bool,
+    #[serde(default)]
+    computed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntrospectedObjectType {
+    name: String,
+    #[serde(rename = "abstract")]
+    is_abstract: bool,
+    #[serde(default)]
+    bases: Vec<Named>,
+    pointers: Vec<IntrospectedPointer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntrospectedScalarType {
+    name: String,
+    enum_values: Option<Vec<String>>,
+    #[serde(default)]
+    bases: Vec<Named>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Introspection {
+    object_types: Vec<IntrospectedObjectType>,
+    #[serde(default)]
+    scalar_types: Vec<IntrospectedScalarType>,
+}
+
+fn name(n: &str) -> String {
+    qualify("default", n)
+}
+
+/// Reads the output of [`INTROSPECTION_QUERY`]. `edgedb query` prints the result set as
+/// an array, both the array and the single object are accepted.
+pub fn schema_from_json(json: &str) -> serde_json::Result<Schema> {
+    let introspection = match serde_json::from_str::<Vec<Introspection>>(json) {
+        Ok(mut v) if v.len() == 1 => v.remove(0),
+        _ => serde_json::from_str::<Introspection>(json)?,
+    };
+
+    let mut schema = Schema::default();
+
+    for ty in introspection.object_types {
+        let ty = ObjectType {
+            name: name(&ty.name),
+            is_abstract: ty.is_abstract,
+            extending: ty.bases.iter().map(|b| name(&b.name)).collect(),
+            pointers: ty
+                .pointers
+                .into_iter()
+                // `id` and `__type__` are there for every type
+                .filter(|p| p.name != "id" && !p.name.starts_with("__"))
+                .map(|p| Pointer {
+                    name: p.name,
+                    target: p.target.map(|t| name(&t.name)).unwrap_or_default(),
+                    is_link: p.is_link,
+                    required: p.required,
+                    multi: p.cardinality == "Many",
+                    computed: p.computed,
+                })
+                .collect(),
+        };
+
+        schema.object_types.insert(ty.name.clone(), ty);
+    }
+
+    for ty in introspection.scalar_types {
+        let ty = ScalarType {
+            name: name(&ty.name),
+            extending: ty.bases.iter().map(|b| name(&b.name)).collect(),
+            enum_values: ty.enum_values,
+        };
+
+        schema.scalar_types.insert(ty.name.clone(), ty);
+    }
+
+    Ok(schema)
+}
+
+#[cfg(test)]
+mod test {
+    use super::schema_from_json;
+
+    #[test]
+    fn reads_introspection_dump() {
+        let schema = schema_from_json(
+            r#"[{
+                "object_types": [{
+                    "name": "default::Outer",
+                    "abstract": false,
+                    "bases": [{"name": "std::Object"}],
+                    "pointers": [
+                        {"name": "id", "required": true, "cardinality": "One",
+                         "target": {"name": "std::uuid"}, "is_link": false, "computed": false},
+                        {"name": "__type__", "required": true, "cardinality": "One",
+                         "target": {"name": "schema::ObjectType"}, "is_link": true, "computed": false},
+                        {"name": "other_field", "required": true, "cardinality": "One",
+                         "target": {"name": "std::str"}, "is_link": false, "computed": false},
+                        {"name": "items", "required": false, "cardinality": "Many",
+                         "target": {"name": "other::Thing"}, "is_link": true, "computed": false}
+                    ]
+                }],
+                "scalar_types": [{
+                    "name": "default::Status",
+                    "enum_values": ["Active", "Inactive"],
+                    "bases": [{"name": "std::anyenum"}]
+                }]
+            }]"#,
+        )
+        .unwrap();
+
+        let outer = schema.object_type("Outer").unwrap();
+        assert_eq!(
+            outer.pointers.iter().map(|p| &*p.name).collect::<Vec<_>>(),
+            ["other_field", "items"]
+        );
+        assert!(outer.pointers[0].required);
+        assert!(outer.pointers[1].multi);
+        assert_eq!(outer.pointers[1].target, "other::Thing");
+
+        assert_eq!(
+            schema.scalar_types["Status"].enum_values.as_deref(),
+            Some(&["Active".to_string(), "Inactive".to_string()][..])
+        );
+    }
+}
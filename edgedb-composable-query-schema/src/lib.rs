@@ -0,0 +1,12 @@
+//! # edgedb-composable-query-schema
+//!
+//! The object types, pointers and scalars of an EdgeDB schema, parsed from the
+//! `dbschema/*.esdl` files ([`esdl`]) or from an introspection dump ([`introspection`]).
+//!
+//! Shared by the compile-time checks of
+//! [edgedb-composable-query-derive](https://docs.rs/edgedb-composable-query-derive), the
+//! drift check of [edgedb-composable-query](https://docs.rs/edgedb-composable-query), and
+//! [edgedb-composable-query-codegen](https://docs.rs/edgedb-composable-query-codegen).
+
+pub mod esdl;
+pub mod introspection;
@@ -0,0 +1,381 @@
+//! Decoding of a 100k-row `Vec<T>` result: the derived `EdgedbObject`, which caches
+//! the shape-to-field mapping, against matching field names for every row. For flat objects,
+//! objects with two links to the same type selected with different shapes, and wide objects.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use edgedb_composable_query::{EdgedbObject, EdgedbSetValue};
+use edgedb_protocol::{
+    codec::{ObjectShape, ShapeElement},
+    value::Value,
+};
+
+const ROWS: usize = 100_000;
+
+#[derive(Debug, PartialEq, EdgedbObject)]
+struct Cached {
+    id: String,
+    name: String,
+    email: Option<String>,
+    age: i64,
+    score: f64,
+    active: bool,
+}
+
+/// What the derive used to generate: `match` on the element name, row by row.
+#[derive(Debug, PartialEq)]
+struct PerRow {
+    id: String,
+    name: String,
+    email: Option<String>,
+    age: i64,
+    score: f64,
+    active: bool,
+}
+
+impl EdgedbObject for PerRow {
+    fn from_edgedb_object(
+        shape: ObjectShape,
+        mut fields: Vec<Option<Value>>,
+    ) -> anyhow::Result<Self> {
+        let mut id = None;
+        let mut name = None;
+        let mut email = None;
+        let mut age = None;
+        let mut score = None;
+        let mut active = None;
+
+        for (i, s) in shape.elements.iter().enumerate() {
+            match s.name.as_str() {
+                "id" => {
+                    id = fields[i]
+                        .take()
+                        .map(EdgedbSetValue::from_edgedb_set_value)
+                        .transpose()?;
+                }
+                "name" => {
+                    name = fields[i]
+                        .take()
+                        .map(EdgedbSetValue::from_edgedb_set_value)
+                        .transpose()?;
+                }
+                "email" => {
+                    email = fields[i]
+                        .take()
+                        .map(EdgedbSetValue::from_edgedb_set_value)
+                        .transpose()?;
+                }
+                "age" => {
+                    age = fields[i]
+                        .take()
+                        .map(EdgedbSetValue::from_edgedb_set_value)
+                        .transpose()?;
+                }
+                "score" => {
+                    score = fields[i]
+                        .take()
+                        .map(EdgedbSetValue::from_edgedb_set_value)
+                        .transpose()?;
+                }
+                "active" => {
+                    active = fields[i]
+                        .take()
+                        .map(EdgedbSetValue::from_edgedb_set_value)
+                        .transpose()?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            id: EdgedbSetValue::interpret_possibly_missing_required_value(id)?,
+            name: EdgedbSetValue::interpret_possibly_missing_required_value(name)?,
+            email: EdgedbSetValue::interpret_possibly_missing_required_value(email)?,
+            age: EdgedbSetValue::interpret_possibly_missing_required_value(age)?,
+            score: EdgedbSetValue::interpret_possibly_missing_required_value(score)?,
+            active: EdgedbSetValue::interpret_possibly_missing_required_value(active)?,
+        })
+    }
+}
+
+/// [`PerRow`], for the other benchmarks: a struct, and its `EdgedbObject` matching the element
+/// names for every row.
+macro_rules! per_row_object {
+    ($name:ident { $( $field:ident: $ty:ty, )* }) => {
+        #[derive(Debug, PartialEq)]
+        struct $name {
+            $( $field: $ty, )*
+        }
+
+        impl EdgedbObject for $name {
+            fn from_edgedb_object(
+                shape: ObjectShape,
+                mut fields: Vec<Option<Value>>,
+            ) -> anyhow::Result<Self> {
+                $( let mut $field = None; )*
+
+                for (i, s) in shape.elements.iter().enumerate() {
+                    match s.name.as_str() {
+                        $(
+                            stringify!($field) => {
+                                $field = fields[i]
+                                    .take()
+                                    .map(EdgedbSetValue::from_edgedb_set_value)
+                                    .transpose()?;
+                            }
+                        )*
+                        _ => {}
+                    }
+                }
+
+                Ok(Self {
+                    $(
+                        $field: EdgedbSetValue::interpret_possibly_missing_required_value($field)?,
+                    )*
+                })
+            }
+        }
+    };
+}
+
+#[derive(Debug, PartialEq, EdgedbObject)]
+struct CachedLink {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, PartialEq, EdgedbObject)]
+struct CachedPair {
+    id: String,
+    a: CachedLink,
+    b: CachedLink,
+}
+
+per_row_object!(PerRowLink {
+    id: String,
+    name: String,
+});
+
+per_row_object!(PerRowPair {
+    id: String,
+    a: PerRowLink,
+    b: PerRowLink,
+});
+
+#[derive(Debug, PartialEq, EdgedbObject)]
+struct CachedWide {
+    f00: i64,
+    f01: i64,
+    f02: i64,
+    f03: i64,
+    f04: i64,
+    f05: i64,
+    f06: i64,
+    f07: i64,
+    f08: i64,
+    f09: i64,
+    f10: i64,
+    f11: i64,
+    f12: i64,
+    f13: i64,
+    f14: i64,
+    f15: i64,
+    f16: i64,
+    f17: i64,
+    f18: i64,
+    f19: i64,
+    f20: i64,
+    f21: i64,
+    f22: i64,
+    f23: i64,
+}
+
+per_row_object!(PerRowWide {
+    f00: i64,
+    f01: i64,
+    f02: i64,
+    f03: i64,
+    f04: i64,
+    f05: i64,
+    f06: i64,
+    f07: i64,
+    f08: i64,
+    f09: i64,
+    f10: i64,
+    f11: i64,
+    f12: i64,
+    f13: i64,
+    f14: i64,
+    f15: i64,
+    f16: i64,
+    f17: i64,
+    f18: i64,
+    f19: i64,
+    f20: i64,
+    f21: i64,
+    f22: i64,
+    f23: i64,
+});
+
+fn shape(names: &[&str]) -> ObjectShape {
+    ObjectShape::new(
+        names
+            .iter()
+            .map(|name| ShapeElement {
+                flag_implicit: *name == "__tid__",
+                flag_link_property: false,
+                flag_link: false,
+                cardinality: None,
+                name: name.to_string(),
+            })
+            .collect(),
+    )
+}
+
+/// Rows as the protocol decodes them: every object shares one shape.
+fn rows() -> Value {
+    let row_shape = shape(&["__tid__", "id", "name", "email", "age", "score", "active"]);
+
+    Value::Set(
+        (0..ROWS)
+            .map(|i| Value::Object {
+                shape: row_shape.clone(),
+                fields: vec![
+                    None,
+                    Some(Value::Str(format!("id-{i}"))),
+                    Some(Value::Str(format!("name-{i}"))),
+                    (i % 2 == 0).then(|| Value::Str(format!("{i}@example.com"))),
+                    Some(Value::Int64(i as i64)),
+                    Some(Value::Float64(i as f64 / 3.0)),
+                    Some(Value::Bool(i % 3 == 0)),
+                ],
+            })
+            .collect(),
+    )
+}
+
+/// Rows with two links to the same type: `a` and `b` have their own shapes, shared by all rows.
+fn pair_rows() -> Value {
+    let row_shape = shape(&["__tid__", "id", "a", "b"]);
+    let a_shape = shape(&["__tid__", "id", "name"]);
+    let b_shape = shape(&["__tid__", "name", "id"]);
+
+    Value::Set(
+        (0..ROWS)
+            .map(|i| Value::Object {
+                shape: row_shape.clone(),
+                fields: vec![
+                    None,
+                    Some(Value::Str(format!("id-{i}"))),
+                    Some(Value::Object {
+                        shape: a_shape.clone(),
+                        fields: vec![
+                            None,
+                            Some(Value::Str(format!("a-{i}"))),
+                            Some(Value::Str(format!("a-name-{i}"))),
+                        ],
+                    }),
+                    Some(Value::Object {
+                        shape: b_shape.clone(),
+                        fields: vec![
+                            None,
+                            Some(Value::Str(format!("b-name-{i}"))),
+                            Some(Value::Str(format!("b-{i}"))),
+                        ],
+                    }),
+                ],
+            })
+            .collect(),
+    )
+}
+
+/// Rows of 24 `int64` properties, where matching the names is most of the decoding.
+fn wide_rows() -> Value {
+    let names = (0..24).map(|i| format!("f{i:02}")).collect::<Vec<_>>();
+    let row_shape = shape(&names.iter().map(String::as_str).collect::<Vec<_>>());
+
+    Value::Set(
+        (0..ROWS)
+            .map(|i| Value::Object {
+                shape: row_shape.clone(),
+                fields: (0..names.len())
+                    .map(|j| Some(Value::Int64((i + j) as i64)))
+                    .collect(),
+            })
+            .collect(),
+    )
+}
+
+fn decode_vec(c: &mut Criterion) {
+    let rows = rows();
+
+    let mut group = c.benchmark_group("decode 100k rows");
+
+    group.bench_function("cached shape plan (derived)", |b| {
+        b.iter_batched(
+            || rows.clone(),
+            |rows| Vec::<Cached>::from_edgedb_set_value(rows).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("match per row", |b| {
+        b.iter_batched(
+            || rows.clone(),
+            |rows| Vec::<PerRow>::from_edgedb_set_value(rows).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn decode_pairs(c: &mut Criterion) {
+    let rows = pair_rows();
+
+    let mut group = c.benchmark_group("decode 100k rows with two shapes of a link");
+
+    group.bench_function("cached shape plan (derived)", |b| {
+        b.iter_batched(
+            || rows.clone(),
+            |rows| Vec::<CachedPair>::from_edgedb_set_value(rows).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("match per row", |b| {
+        b.iter_batched(
+            || rows.clone(),
+            |rows| Vec::<PerRowPair>::from_edgedb_set_value(rows).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn decode_wide(c: &mut Criterion) {
+    let rows = wide_rows();
+
+    let mut group = c.benchmark_group("decode 100k rows of 24 properties");
+
+    group.bench_function("cached shape plan (derived)", |b| {
+        b.iter_batched(
+            || rows.clone(),
+            |rows| Vec::<CachedWide>::from_edgedb_set_value(rows).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("match per row", |b| {
+        b.iter_batched(
+            || rows.clone(),
+            |rows| Vec::<PerRowWide>::from_edgedb_set_value(rows).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, decode_vec, decode_pairs, decode_wide);
+criterion_main!(benches);
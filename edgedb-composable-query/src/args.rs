@@ -1,22 +1,155 @@
 use crate::prim::EdgedbPrim;
 use crate::EdgedbValue;
-use crate::Result;
+use crate::{EdgedbObject, Ref, Result};
+use edgedb_protocol::model::{Json, LocalDatetime};
 use edgedb_protocol::query_arg::QueryArgs;
 use edgedb_protocol::value::Value;
 
-/// A tuple of query arguments, currently each must be [`EdgedbPrim`].
+/// A single query argument: an [`EdgedbPrim`], a [`Ref`] (passed as its `uuid`),
+/// an `Option` of those for `<optional ...>` arguments, or a `Vec` of those passed as `<json>`.
+pub trait EdgedbQueryArg {
+    /// [`EdgedbPrim::TYPE_CAST`], without `optional`
+    const TYPE_CAST: &'static str;
+    const OPTIONAL: bool = false;
+    /// Passed as `<optional array<...>>`, see [`Patch`](crate::composable::Patch)
+    const PATCH: bool = false;
+    /// A `Vec`, passed as a `<json>` array of [`ELEMENT_CAST`](Self::ELEMENT_CAST)s
+    const MULTI: bool = false;
+    const ELEMENT_CAST: &'static str = Self::TYPE_CAST;
+    /// `+=`, `-=` or `:=` of a [`LinkOp`](crate::composable::LinkOp), passed as `<array<...>>`
+    #[doc(hidden)]
+    const LINK_OPERATOR: Option<&'static str> = None;
+    /// `insert T { ... }` of a nested [`EdgedbInsert`](crate::composable::EdgedbInsert), with
+    /// its fields from the JSON at the given expression
+    #[doc(hidden)]
+    const NESTED_INSERT: Option<fn(&str) -> String> = None;
+
+    fn to_query_arg(self) -> Result<Value>;
+}
+
+impl<T: EdgedbPrim> EdgedbQueryArg for T {
+    const TYPE_CAST: &'static str = T::TYPE_CAST;
+
+    fn to_query_arg(self) -> Result<Value> {
+        self.to_edgedb_val()
+    }
+}
+
+impl<T: EdgedbObject> EdgedbQueryArg for Ref<T> {
+    const TYPE_CAST: &'static str = "uuid";
+
+    fn to_query_arg(self) -> Result<Value> {
+        Ok(Value::Uuid(self.id))
+    }
+}
+
+impl<T: EdgedbQueryArg> EdgedbQueryArg for Option<T> {
+    const TYPE_CAST: &'static str = T::TYPE_CAST;
+    const OPTIONAL: bool = true;
+    const MULTI: bool = T::MULTI;
+    const ELEMENT_CAST: &'static str = T::ELEMENT_CAST;
+    const NESTED_INSERT: Option<fn(&str) -> String> = T::NESTED_INSERT;
+
+    fn to_query_arg(self) -> Result<Value> {
+        match self {
+            Some(v) => v.to_query_arg(),
+            None => Ok(Value::Nothing),
+        }
+    }
+}
+
+impl<T: EdgedbQueryArg> EdgedbQueryArg for Vec<T> {
+    const TYPE_CAST: &'static str = "json";
+    const MULTI: bool = true;
+    const ELEMENT_CAST: &'static str = T::TYPE_CAST;
+    const NESTED_INSERT: Option<fn(&str) -> String> = T::NESTED_INSERT;
+
+    fn to_query_arg(self) -> Result<Value> {
+        let items = self
+            .into_iter()
+            .map(|v| json_of(v.to_query_arg()?))
+            .collect::<Result<Vec<_>>>()?;
+
+        let val = serde_json::to_string(&items)?;
+
+        // safety: we just serialized this value
+        Ok(Value::Json(unsafe { Json::new_unchecked(val) }))
+    }
+}
+
+/// The JSON of a scalar, a `uuid`, or a `json` value
+pub(crate) fn json_of(value: Value) -> Result<serde_json::Value> {
+    Ok(match value {
+        Value::Nothing => serde_json::Value::Null,
+        Value::Str(s) => s.into(),
+        Value::Bool(b) => b.into(),
+        Value::Int16(i) => i.into(),
+        Value::Int32(i) => i.into(),
+        Value::Int64(i) => i.into(),
+        Value::Float32(f) => f.into(),
+        Value::Float64(f) => f.into(),
+        Value::Uuid(id) => id.to_string().into(),
+        Value::Json(json) => serde_json::from_str(&json)?,
+        // ISO 8601, which the `<json>` casts to these types expect
+        Value::Datetime(datetime) => {
+            let local = LocalDatetime::from(datetime);
+            format!("{}T{}Z", local.date(), local.time()).into()
+        }
+        Value::LocalDatetime(local) => format!("{}T{}", local.date(), local.time()).into(),
+        Value::LocalDate(date) => date.to_string().into(),
+        Value::LocalTime(time) => time.to_string().into(),
+        value => return Err(anyhow::anyhow!("can't pass {value:?} in JSON")),
+    })
+}
+
+/// `<str>$0`, `<optional str>$0` for `Option`s, `<optional array<str>>$0` for `Patch`es,
+/// `<array<uuid>>$0` for `LinkOp`s
+#[doc(hidden)]
+pub fn __query_arg<T: EdgedbQueryArg>(placeholder: &str) -> String {
+    match (T::PATCH, T::OPTIONAL) {
+        _ if T::LINK_OPERATOR.is_some() => format!("<array<{}>>{placeholder}", T::TYPE_CAST),
+        (true, _) => format!("<optional array<{}>>{placeholder}", T::TYPE_CAST),
+        (false, true) => format!("<optional {}>{placeholder}", T::TYPE_CAST),
+        (false, false) => format!("<{}>{placeholder}", T::TYPE_CAST),
+    }
+}
+
+/// A tuple of query arguments, each an [`EdgedbQueryArg`].
 pub trait EdgedbQueryArgs {
     type EdgedbArgsType: QueryArgs;
 
+    /// The cast of each argument in the query, like `<str>` or `<optional str>`. None by
+    /// default: the exported `.edgeql` header then lists the arguments without them.
+    fn type_casts() -> Vec<String> {
+        Vec::new()
+    }
+
     fn to_query_args(self) -> Result<Self::EdgedbArgsType>;
+
+    /// [`EdgedbQueryArg::to_query_arg`] of each argument, for a
+    /// [`bulk_insert`](crate::composable::bulk_insert). Unsupported by default.
+    fn to_query_values(self) -> Result<Vec<Value>>
+    where
+        Self: Sized,
+    {
+        anyhow::bail!("these arguments can't be listed as values")
+    }
 }
 
 impl EdgedbQueryArgs for () {
     type EdgedbArgsType = ();
 
+    fn type_casts() -> Vec<String> {
+        Vec::new()
+    }
+
     fn to_query_args(self) -> Result<Self::EdgedbArgsType> {
         Ok(self)
     }
+
+    fn to_query_values(self) -> Result<Vec<Value>> {
+        Ok(Vec::new())
+    }
 }
 
 macro_rules! ignore_first {
@@ -28,13 +161,23 @@ macro_rules! ignore_first {
 macro_rules! impl_tuple {
     ( $count:expr, ($($name:ident,)+), ($($small_name:ident,)+) ) => (
 
-        impl<$($name:EdgedbPrim),+> EdgedbQueryArgs for ($($name,)+) {
+        impl<$($name:EdgedbQueryArg),+> EdgedbQueryArgs for ($($name,)+) {
             type EdgedbArgsType = ($(ignore_first!($name, Value),)+);
 
+            fn type_casts() -> Vec<String> {
+                vec![$(__query_arg::<$name>("")),+]
+            }
+
             fn to_query_args(self) -> Result<Self::EdgedbArgsType> {
                 let ($($small_name,)+) = self;
 
-                Ok(($($small_name.to_edgedb_val()?,)+))
+                Ok(($($small_name.to_query_arg()?,)+))
+            }
+
+            fn to_query_values(self) -> Result<Vec<Value>> {
+                let ($($small_name,)+) = self;
+
+                Ok(vec![$($small_name.to_query_arg()?,)+])
             }
         }
 
@@ -47,6 +190,12 @@ impl_tuple! {3, (T0, T1, T2,), (t0, t1, t2,)}
 impl_tuple! {4, (T0, T1, T2, T3,), (t0, t1, t2, t3,)}
 impl_tuple! {5, (T0, T1, T2, T3, T4,), (t0, t1, t2, t3, t4,)}
 impl_tuple! {6, (T0, T1, T2, T3, T4, T5,), (t0, t1, t2, t3, t4, t5,)}
+impl_tuple! {7, (T0, T1, T2, T3, T4, T5, T6,), (t0, t1, t2, t3, t4, t5, t6,)}
+impl_tuple! {8, (T0, T1, T2, T3, T4, T5, T6, T7,), (t0, t1, t2, t3, t4, t5, t6, t7,)}
+impl_tuple! {9, (T0, T1, T2, T3, T4, T5, T6, T7, T8,), (t0, t1, t2, t3, t4, t5, t6, t7, t8,)}
+impl_tuple! {10, (T0, T1, T2, T3, T4, T5, T6, T7, T8, T9,), (t0, t1, t2, t3, t4, t5, t6, t7, t8, t9,)}
+impl_tuple! {11, (T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10,), (t0, t1, t2, t3, t4, t5, t6, t7, t8, t9, t10,)}
+impl_tuple! {12, (T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11,), (t0, t1, t2, t3, t4, t5, t6, t7, t8, t9, t10, t11,)}
 
 #[cfg(test)]
 mod test {
@@ -0,0 +1,135 @@
+use std::marker::PhantomData;
+
+use super::EdgedbComposableQuery;
+
+/// Derived for every [`EdgedbComposableQuery`] with a `#[select]` or `#[direct]`: the same
+/// selection, params and `with`s, counted or checked for existence. The `#[filter]`s apply,
+/// the shape, `#[order_by]`, `#[offset]` and `#[limit]` don't, so [`Count`] is the total of
+/// a paginated query.
+///
+/// ```
+/// use edgedb_composable_query::{
+///     composable::{EdgedbComposableQuery, EdgedbComposableSelector, EdgedbSelectQuery},
+///     EdgedbObject,
+/// };
+///
+/// #[derive(EdgedbObject, EdgedbComposableSelector)]
+/// struct Inner {
+///     req: String,
+/// }
+///
+/// #[derive(EdgedbComposableQuery)]
+/// #[params(n: i64)]
+/// #[select("Inner")]
+/// #[filter("exists .opt")]
+/// #[limit(n)]
+/// struct SomeInners(Vec<Inner>);
+///
+/// assert_eq!(
+///     SomeInners::count_query(),
+///     "with\n\tn := <int64>$0,\n\t_selector := (Inner),\nselect count((select (_selector)\nfilter exists .opt))"
+/// );
+/// ```
+pub trait EdgedbSelectQuery: EdgedbComposableQuery + Sized {
+    /// `select function((select ... filter ...))`
+    fn format_aggregate(
+        fmt: &mut impl std::fmt::Write,
+        args: &::std::collections::HashMap<&str, String>,
+        function: &str,
+    ) -> Result<(), std::fmt::Error>;
+
+    /// The query of [`Count<Self>`]
+    fn count_query() -> String {
+        Count::<Self>::query()
+    }
+
+    /// The query of [`Exists<Self>`]
+    fn exists_query() -> String {
+        Exists::<Self>::query()
+    }
+}
+
+/// `select count(...)` of a query, with the same params: `run_query::<Count<Q>>(&conn, args)`.
+pub struct Count<Q>(PhantomData<Q>);
+
+/// `select exists(...)` of a query, with the same params: `run_query::<Exists<Q>>(&conn, args)`.
+pub struct Exists<Q>(PhantomData<Q>);
+
+impl<Q: EdgedbSelectQuery> EdgedbComposableQuery for Count<Q> {
+    const ARG_NAMES: &'static [&'static str] = Q::ARG_NAMES;
+
+    type ArgTypes = Q::ArgTypes;
+    type ReturnType = i64;
+
+    fn format_query(
+        fmt: &mut impl std::fmt::Write,
+        args: &::std::collections::HashMap<&str, String>,
+    ) -> Result<(), std::fmt::Error> {
+        Q::format_aggregate(fmt, args, "count")
+    }
+}
+
+impl<Q: EdgedbSelectQuery> EdgedbComposableQuery for Exists<Q> {
+    const ARG_NAMES: &'static [&'static str] = Q::ARG_NAMES;
+
+    type ArgTypes = Q::ArgTypes;
+    type ReturnType = bool;
+
+    fn format_query(
+        fmt: &mut impl std::fmt::Write,
+        args: &::std::collections::HashMap<&str, String>,
+    ) -> Result<(), std::fmt::Error> {
+        Q::format_aggregate(fmt, args, "exists")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Count, EdgedbSelectQuery, Exists};
+    use crate::{
+        composable::{run_query, EdgedbComposableQuery, EdgedbComposableSelector},
+        EdgedbObject,
+    };
+
+    #[allow(unused)]
+    #[derive(EdgedbObject, EdgedbComposableSelector)]
+    struct InnerSelector {
+        req: String,
+    }
+
+    #[allow(unused)]
+    #[derive(EdgedbComposableQuery)]
+    #[params(req: String, n: i64)]
+    #[select("Inner")]
+    #[filter(".req = req")]
+    #[order_by(".req")]
+    #[limit(n)]
+    struct InnersPage(Vec<InnerSelector>);
+
+    #[allow(unused)]
+    #[derive(EdgedbComposableQuery)]
+    #[select("Inner")]
+    struct AllInners(Vec<InnerSelector>);
+
+    #[test]
+    fn aggregate_queries() {
+        insta::assert_snapshot!(InnersPage::count_query());
+        insta::assert_snapshot!(AllInners::exists_query());
+    }
+
+    #[tokio::test]
+    async fn counts() -> anyhow::Result<()> {
+        let conn = edgedb_tokio::create_client().await?;
+
+        let page = run_query::<InnersPage>(&conn, ("hi".to_string(), 1)).await?;
+        let total = run_query::<Count<InnersPage>>(&conn, ("hi".to_string(), 1)).await?;
+
+        assert!(page.len() as i64 <= total);
+        assert_eq!(
+            run_query::<Exists<AllInners>>(&conn, ()).await?,
+            run_query::<Count<AllInners>>(&conn, ()).await? > 0
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,422 @@
+use std::fmt;
+
+use edgedb_composable_query_schema::{
+    esdl::{qualify, ObjectType, Schema},
+    introspection::{schema_from_json, INTROSPECTION_QUERY},
+};
+use edgedb_protocol::server_message::Cardinality;
+use edgedb_tokio::Client;
+
+use crate::{describe::cardinality_name, Result};
+
+use super::EdgedbComposableSelector;
+
+/// What a selector's field points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectorTarget {
+    /// A property, with the `EdgedbPrim::TYPE_CAST`
+    Scalar(&'static str),
+    /// A link, with the `#[edgedb(type = "...")]` of the nested selector, if it has one
+    Object(Option<&'static str>),
+}
+
+/// A field of a derived selector, see [`EdgedbComposableSelector::fields`].
+#[derive(Debug, Clone, Copy)]
+pub struct SelectorField {
+    pub name: &'static str,
+    pub target: SelectorTarget,
+    pub cardinality: Cardinality,
+    /// The nested selector's fields, empty for properties
+    pub fields: fn() -> Vec<SelectorField>,
+}
+
+impl SelectorField {
+    #[doc(hidden)]
+    pub const fn new<T: EdgedbComposableSelector>(name: &'static str) -> Self {
+        Self {
+            name,
+            target: T::TARGET,
+            cardinality: T::CARDINALITY,
+            fields: T::fields,
+        }
+    }
+
+    /// A `#[recursive]` field: its fields are the ones of the selector it's in
+    #[doc(hidden)]
+    pub const fn recursive<T: EdgedbComposableSelector>(name: &'static str) -> Self {
+        Self {
+            fields: Vec::new,
+            ..Self::new::<T>(name)
+        }
+    }
+}
+
+/// An [`EdgedbComposableSelector`] with `#[edgedb(type = "...")]`, registered automatically
+/// by its derive. See [`schema_drift`].
+pub struct RegisteredSelector {
+    pub type_name: &'static str,
+    pub schema_type: &'static str,
+    pub fields: fn() -> Vec<SelectorField>,
+}
+
+inventory::collect!(RegisteredSelector);
+
+impl RegisteredSelector {
+    #[doc(hidden)]
+    pub const fn new<T: EdgedbComposableSelector>(
+        type_name: &'static str,
+        schema_type: &'static str,
+    ) -> Self {
+        Self {
+            type_name,
+            schema_type,
+            fields: T::fields,
+        }
+    }
+}
+
+/// All the selectors with a schema type in the binary, sorted by their type names.
+pub fn registered_selectors() -> Vec<&'static RegisteredSelector> {
+    let mut selectors = inventory::iter::<RegisteredSelector>
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    selectors.sort_by_key(|s| s.type_name);
+
+    selectors
+}
+
+/// How a selector disagrees with the schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriftProblem {
+    /// The schema has no such object type
+    MissingType,
+    /// The type has no such property or link
+    MissingPointer,
+    /// A link selected as a primitive, or a property selected as an object
+    KindMismatch { schema_is_link: bool },
+    /// Different scalar types, or a link to a different object type
+    TargetMismatch { schema: String, rust: String },
+    /// `schema` is what `required` and `multi` allow
+    CardinalityMismatch {
+        schema: Cardinality,
+        rust: Cardinality,
+    },
+}
+
+/// A problem found by [`schema_drift`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaDrift {
+    /// The Rust type of the registered selector
+    pub selector: &'static str,
+    /// `Type` or `Type.field.nested_field`
+    pub path: String,
+    pub problem: DriftProblem,
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: ", self.selector, self.path)?;
+
+        match &self.problem {
+            DriftProblem::MissingType => write!(f, "no such type in the schema"),
+            DriftProblem::MissingPointer => write!(f, "no such property or link in the schema"),
+            DriftProblem::KindMismatch {
+                schema_is_link: true,
+            } => write!(f, "is a link, but selected as a primitive"),
+            DriftProblem::KindMismatch {
+                schema_is_link: false,
+            } => write!(f, "is a property, but selected as an object"),
+            DriftProblem::TargetMismatch { schema, rust } => {
+                write!(f, "is {schema} in the schema, but {rust} in Rust")
+            }
+            DriftProblem::CardinalityMismatch { schema, rust } => write!(
+                f,
+                "is {} in the schema, but {} in Rust",
+                cardinality_name(*schema),
+                cardinality_name(*rust)
+            ),
+        }
+    }
+}
+
+fn is_multi(c: Cardinality) -> bool {
+    matches!(c, Cardinality::Many | Cardinality::AtLeastOne)
+}
+
+fn is_required(c: Cardinality) -> bool {
+    matches!(c, Cardinality::One | Cardinality::AtLeastOne)
+}
+
+fn diff_fields(
+    schema: &Schema,
+    selector: &'static str,
+    path: &str,
+    object: &ObjectType,
+    fields: &[SelectorField],
+    into: &mut Vec<SchemaDrift>,
+) {
+    for field in fields {
+        // implicit in every type
+        if field.name == "id" {
+            continue;
+        }
+
+        let path = format!("{path}.{}", field.name);
+        let mut push = |problem| {
+            into.push(SchemaDrift {
+                selector,
+                path: path.clone(),
+                problem,
+            })
+        };
+
+        let Some(pointer) = schema.pointer(object, field.name) else {
+            push(DriftProblem::MissingPointer);
+            continue;
+        };
+
+        let schema_cardinality = match (pointer.required, pointer.multi) {
+            (true, false) => Cardinality::One,
+            (true, true) => Cardinality::AtLeastOne,
+            (false, false) => Cardinality::AtMostOne,
+            (false, true) => Cardinality::Many,
+        };
+
+        // a computed pointer's requiredness isn't known without its expression
+        if is_multi(field.cardinality) != pointer.multi
+            || (is_required(field.cardinality) && !pointer.required && !pointer.computed)
+        {
+            push(DriftProblem::CardinalityMismatch {
+                schema: schema_cardinality,
+                rust: field.cardinality,
+            });
+        }
+
+        match field.target {
+            // a computed pointer without a known type
+            _ if pointer.target.is_empty() => {}
+            SelectorTarget::Scalar(_) if pointer.is_link => {
+                push(DriftProblem::KindMismatch {
+                    schema_is_link: true,
+                });
+            }
+            SelectorTarget::Object(_) if !pointer.is_link => {
+                push(DriftProblem::KindMismatch {
+                    schema_is_link: false,
+                });
+            }
+            SelectorTarget::Scalar(cast) => {
+                let cast = qualify("default", cast);
+
+                if cast != pointer.target && schema.base_scalar(&pointer.target) != Some(&cast) {
+                    push(DriftProblem::TargetMismatch {
+                        schema: pointer.target.clone(),
+                        rust: cast,
+                    });
+                }
+            }
+            SelectorTarget::Object(Some(type_name)) => {
+                // registered on its own, and checked separately
+                if qualify("default", type_name) != pointer.target {
+                    push(DriftProblem::TargetMismatch {
+                        schema: pointer.target.clone(),
+                        rust: type_name.to_string(),
+                    });
+                }
+            }
+            SelectorTarget::Object(None) => {
+                if let Some(target) = schema.object_type(&pointer.target) {
+                    diff_fields(schema, selector, &path, target, &(field.fields)(), into);
+                }
+            }
+        }
+    }
+}
+
+/// Compares every [registered selector](registered_selectors) against `schema`. See
+/// [`schema_drift`] for the live one; this also works with the `.esdl` files.
+pub fn diff_selectors(schema: &Schema) -> Vec<SchemaDrift> {
+    let mut drift = Vec::new();
+
+    for selector in registered_selectors() {
+        let Some(object) = schema.object_type(selector.schema_type) else {
+            drift.push(SchemaDrift {
+                selector: selector.type_name,
+                path: selector.schema_type.to_string(),
+                problem: DriftProblem::MissingType,
+            });
+            continue;
+        };
+
+        diff_fields(
+            schema,
+            selector.type_name,
+            selector.schema_type,
+            object,
+            &(selector.fields)(),
+            &mut drift,
+        );
+    }
+
+    drift
+}
+
+/// Introspect the server's schema, and compare every selector with `#[edgedb(type = "...")]`
+/// against it: field names, scalar and link targets, `required` and `multi`. Nested selectors
+/// without a type are checked as a part of the selector that uses them.
+///
+/// The compile-time check only sees `dbschema`, run this after deploying a migration:
+///
+/// ```no_run
+/// # tokio_test::block_on(async {
+/// use edgedb_composable_query::composable::schema_drift;
+///
+/// let conn = edgedb_tokio::create_client().await?;
+///
+/// for problem in schema_drift(&conn).await? {
+///     eprintln!("{problem}");
+/// }
+/// # anyhow::Ok(())
+/// # }).unwrap();
+/// ```
+pub async fn schema_drift(client: &Client) -> Result<Vec<SchemaDrift>> {
+    let json = client
+        .query_required_single_json(INTROSPECTION_QUERY, &())
+        .await?;
+
+    let schema = schema_from_json(&json)?;
+
+    Ok(diff_selectors(&schema))
+}
+
+#[cfg(test)]
+mod test {
+    use edgedb_composable_query_schema::esdl::Schema;
+
+    use super::{diff_selectors, schema_drift, DriftProblem, SchemaDrift};
+    use crate::{
+        composable::{Cardinality, EdgedbComposableSelector},
+        EdgedbObject,
+    };
+
+    #[allow(unused)]
+    #[derive(EdgedbObject, EdgedbComposableSelector)]
+    struct NestedInner {
+        req: String,
+    }
+
+    #[allow(unused)]
+    #[derive(EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Outer")]
+    struct DriftOuter {
+        other_field: String,
+        some_field: Option<String>,
+        inner: Option<NestedInner>,
+        a: Option<NestedInner>,
+
+        #[var("len(.other_field)")]
+        strlen: i64,
+    }
+
+    fn drift_of(selector: &str, esdl: &str) -> Vec<SchemaDrift> {
+        let mut schema = Schema::default();
+        schema.parse_into(esdl).unwrap();
+
+        diff_selectors(&schema)
+            .into_iter()
+            .filter(|d| d.selector.ends_with(selector))
+            .collect()
+    }
+
+    #[test]
+    fn no_drift_from_dbschema() {
+        let drift = drift_of("", include_str!("../../../dbschema/default.esdl"));
+
+        assert_eq!(drift, []);
+    }
+
+    #[test]
+    fn drift_problems() {
+        let drift = drift_of(
+            "DriftOuter",
+            r#"
+            module default {
+                type Inner {
+                    required req: int64;
+                }
+                type Outer {
+                    multi inner: Inner;
+                    some_field: str;
+                    other_field: str;
+                    a: str;
+                }
+            }
+            "#,
+        );
+
+        assert_eq!(
+            drift.iter().map(|d| d.problem.clone()).collect::<Vec<_>>(),
+            [
+                DriftProblem::CardinalityMismatch {
+                    schema: Cardinality::AtMostOne,
+                    rust: Cardinality::One,
+                },
+                DriftProblem::CardinalityMismatch {
+                    schema: Cardinality::Many,
+                    rust: Cardinality::AtMostOne,
+                },
+                DriftProblem::TargetMismatch {
+                    schema: "int64".to_string(),
+                    rust: "str".to_string(),
+                },
+                DriftProblem::KindMismatch {
+                    schema_is_link: false,
+                },
+            ]
+        );
+
+        assert_eq!(
+            drift.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "edgedb_composable_query::composable::drift::test::DriftOuter: Outer.other_field: is AT_MOST_ONE in the schema, but ONE in Rust",
+                "edgedb_composable_query::composable::drift::test::DriftOuter: Outer.inner: is MANY in the schema, but AT_MOST_ONE in Rust",
+                "edgedb_composable_query::composable::drift::test::DriftOuter: Outer.inner.req: is int64 in the schema, but str in Rust",
+                "edgedb_composable_query::composable::drift::test::DriftOuter: Outer.a: is a property, but selected as an object",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_type_and_pointer() {
+        let drift = drift_of(
+            "DriftOuter",
+            "module default { type Outer { required other_field: str; } }",
+        );
+
+        assert_eq!(
+            drift
+                .iter()
+                .map(|d| (&*d.path, d.problem.clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("Outer.some_field", DriftProblem::MissingPointer),
+                ("Outer.inner", DriftProblem::MissingPointer),
+                ("Outer.a", DriftProblem::MissingPointer),
+            ]
+        );
+
+        let drift = drift_of("DriftOuter", "module default { type Other {} }");
+        assert_eq!(drift.len(), 1);
+        assert_eq!(drift[0].problem, DriftProblem::MissingType);
+    }
+
+    #[tokio::test]
+    async fn no_drift_from_server() -> anyhow::Result<()> {
+        let conn = edgedb_tokio::create_client().await?;
+
+        assert_eq!(schema_drift(&conn).await?, []);
+
+        Ok(())
+    }
+}
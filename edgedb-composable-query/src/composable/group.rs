@@ -0,0 +1,179 @@
+use edgedb_protocol::{codec::ObjectShape, value::Value};
+
+use crate::{EdgedbObject, EdgedbSetValue, EdgedbValue, Result};
+
+use super::{ComposableQueryResultKind, EdgedbComposableSelector};
+
+/// One group of a `group ... by ...` query: `#[group(by = "...")]` on an
+/// [`EdgedbComposableQuery`](super::EdgedbComposableQuery) returning `Vec<Grouped<K, T>>`.
+///
+/// `K` is an [`EdgedbObject`] with a field for each grouping key, and `T` is the selector of
+/// the elements, written as the shape of the `group`.
+///
+/// ```
+/// use edgedb_composable_query::{
+///     composable::{EdgedbComposableQuery, EdgedbComposableSelector, Grouped},
+///     EdgedbObject,
+/// };
+///
+/// #[derive(EdgedbObject)]
+/// struct ByOpt {
+///     opt: Option<String>,
+/// }
+///
+/// #[derive(EdgedbObject, EdgedbComposableSelector)]
+/// struct InnerSelector {
+///     req: String,
+/// }
+///
+/// #[derive(EdgedbComposableQuery)]
+/// #[select("Inner")]
+/// #[group(by = ".opt")]
+/// struct InnersByOpt(Vec<Grouped<ByOpt, InnerSelector>>);
+///
+/// assert_eq!(
+///     InnersByOpt::query(),
+///     "with\n\t_selector := (Inner),\ngroup (_selector) {\n\treq := (.req),\n}\nby .opt"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grouped<K, T> {
+    pub key: K,
+    /// The names of the keys this group is grouped by
+    pub grouping: Vec<String>,
+    pub elements: Vec<T>,
+}
+
+impl<K: EdgedbValue, T: EdgedbValue> EdgedbObject for Grouped<K, T> {
+    fn from_edgedb_object(shape: ObjectShape, mut fields: Vec<Option<Value>>) -> Result<Self> {
+        thread_local! {
+            static SHAPE_PLANS: std::cell::RefCell<Vec<crate::shape::ShapePlan>> =
+                const { std::cell::RefCell::new(Vec::new()) };
+        }
+
+        let plan =
+            crate::shape::ShapePlan::cached(&SHAPE_PLANS, &shape, &["key", "grouping", "elements"]);
+
+        let mut take = |i: usize| plan[i].and_then(|i| fields[i].take());
+
+        Ok(Self {
+            key: K::interpret_possibly_missing_required_value(
+                take(0).map(K::from_edgedb_set_value).transpose()?,
+            )?,
+            grouping: EdgedbSetValue::interpret_possibly_missing_required_value(
+                take(1)
+                    .map(EdgedbSetValue::from_edgedb_set_value)
+                    .transpose()?,
+            )?,
+            elements: EdgedbSetValue::interpret_possibly_missing_required_value(
+                take(2)
+                    .map(EdgedbSetValue::from_edgedb_set_value)
+                    .transpose()?,
+            )?,
+        })
+    }
+
+    fn object_fields() -> Vec<crate::ObjectField> {
+        vec![
+            crate::ObjectField::new::<K>("key"),
+            crate::ObjectField::new::<Vec<String>>("grouping"),
+            crate::ObjectField::new::<Vec<T>>("elements"),
+        ]
+    }
+}
+
+/// The shape of the grouped elements
+impl<K, T: EdgedbComposableSelector> EdgedbComposableSelector for Grouped<K, T> {
+    const RESULT_TYPE: ComposableQueryResultKind = ComposableQueryResultKind::Selector;
+
+    fn format_selector(fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error> {
+        T::format_selector(fmt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Grouped;
+    use crate::{
+        composable::{
+            bulk_insert, run_delete, run_query, EdgedbComposableQuery, EdgedbComposableSelector,
+            EdgedbDelete, EdgedbInsert,
+        },
+        EdgedbObject,
+    };
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject)]
+    struct ByOpt {
+        opt: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    struct InnerSelector {
+        req: String,
+    }
+
+    #[allow(unused)]
+    #[derive(EdgedbComposableQuery)]
+    #[params(req: String)]
+    #[select("select Inner filter .req = req")]
+    #[group(by = ".opt")]
+    struct InnersByOpt(Vec<Grouped<ByOpt, InnerSelector>>);
+
+    #[derive(EdgedbInsert)]
+    #[edgedb(type = "Inner")]
+    struct NewInner {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[derive(EdgedbDelete)]
+    #[edgedb(type = "Inner")]
+    struct DeleteInners {
+        req: String,
+    }
+
+    #[test]
+    fn group_query() {
+        insta::assert_snapshot!(InnersByOpt::query());
+    }
+
+    #[tokio::test]
+    async fn groups() -> anyhow::Result<()> {
+        let conn = edgedb_tokio::create_client().await?;
+
+        let req = || "to group".to_string();
+
+        run_delete(&conn, DeleteInners { req: req() }).await?;
+
+        bulk_insert(
+            &conn,
+            [Some("a"), Some("a"), None].map(|opt| NewInner {
+                req: req(),
+                opt: opt.map(str::to_string),
+            }),
+        )
+        .await?;
+
+        let mut groups = run_query::<InnersByOpt>(&conn, (req(),)).await?;
+        groups.sort_by_key(|g| g.key.opt.clone());
+
+        assert_eq!(
+            groups
+                .iter()
+                .map(|g| (
+                    g.key.opt.as_deref(),
+                    g.grouping.as_slice(),
+                    g.elements.len()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (None, &["opt".to_string()][..], 1),
+                (Some("a"), &["opt".to_string()][..], 2),
+            ]
+        );
+
+        assert_eq!(run_delete(&conn, DeleteInners { req: req() }).await?, 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+use edgedb_protocol::{
+    model::{Json, Uuid},
+    value::Value,
+};
+use edgedb_tokio::Client;
+
+use crate::{args::json_of, EdgedbJson, EdgedbQueryArg, EdgedbQueryArgs, Result};
+
+use super::{run_query, EdgedbComposableQuery};
+
+pub use edgedb_composable_query_derive::EdgedbInsert;
+
+/// Derivable: `insert` the struct, one parameter per field. Runs with [`run_insert`], or
+/// [`bulk_insert`] for many at once.
+///
+/// - `#[edgedb(type = "Name")]`: the type to insert, required
+/// - `#[edgedb(returning = "Selector")]`: select it after inserting, instead of the `id`
+/// - `Option` fields are `<optional ...>` parameters
+/// - `Ref<T>` fields, and `Uuid` fields with `#[edgedb(link = "Target")]`, are links:
+///   `(select Target filter .id = <uuid>$n)`. A `Ref<T>`'s target comes from the schema, or
+///   `#[edgedb(link = "Target")]` if it isn't there
+/// - fields that are `EdgedbInsert`s themselves are nested inserts, in the same statement:
+///   `(for inner_item in {<json>$n} union (insert Inner { ... }))`
+/// - `Vec` fields are sets, passed as `<json>` arrays: of properties, of links, or of nested
+///   inserts
+/// - `#[upsert(conflict_on = ".email")]`: an upsert, `unless conflict on .email` updates the
+///   existing object's other fields instead, and returns it
+///
+/// ```
+/// use edgedb_composable_query::{composable::{EdgedbComposableQuery, EdgedbInsert}, EdgedbObject, Ref};
+///
+/// #[derive(EdgedbObject)]
+/// struct Inner {
+///     req: String,
+/// }
+///
+/// #[derive(EdgedbInsert)]
+/// #[edgedb(type = "Outer")]
+/// struct NewOuter {
+///     other_field: String,
+///     some_field: Option<String>,
+///     inner: Option<Ref<Inner>>,
+/// }
+///
+/// assert_eq!(
+///     NewOuter::query(),
+///     "select (insert Outer {
+/// \tother_field := <str>$0,
+/// \tsome_field := <optional str>$1,
+/// \tinner := (select Inner filter .id = <optional uuid>$2),
+/// }).id"
+/// );
+/// ```
+pub trait EdgedbInsert: EdgedbComposableQuery {
+    /// The fields, in the order of [`EdgedbComposableQuery::ARG_NAMES`]
+    fn into_args(self) -> Self::ArgTypes;
+
+    /// `insert T { ... }`, with the fields from the JSON object at `item`
+    fn format_json_insert(
+        fmt: &mut impl std::fmt::Write,
+        item: &str,
+    ) -> Result<(), std::fmt::Error>;
+
+    /// The insert for every object of a `<json>$0` array, see [`bulk_insert`]
+    fn bulk_query() -> String {
+        let mut insert = String::new();
+        Self::format_json_insert(&mut insert, "item").unwrap();
+
+        // the results of a `for` are a set, so each id comes with the index of its item
+        format!(
+            "for entry in enumerate(json_array_unpack(<json>$0)) union (\n\
+             \twith\n\
+             \t\titem := entry.1,\n\
+             \t\tinserted := ({}),\n\
+             \tselect (entry.0, inserted.id)\n\
+             )",
+            crate::__query_add_indent(&crate::__query_add_indent(&insert))
+        )
+    }
+}
+
+/// Insert `value`, and return its `id` or the `returning` selector.
+pub async fn run_insert<T: EdgedbInsert>(client: &Client, value: T) -> Result<T::ReturnType>
+where
+    <T as EdgedbComposableQuery>::ArgTypes: Send,
+{
+    run_query::<T>(client, value.into_args()).await
+}
+
+/// The fields of `value` as a JSON object
+fn json_object<T: EdgedbInsert>(value: T) -> Result<serde_json::Value> {
+    let fields = value.into_args().to_query_values()?;
+
+    T::ARG_NAMES
+        .iter()
+        .zip(fields)
+        .map(|(name, field)| Ok((name.to_string(), json_of(field)?)))
+        .collect::<Result<serde_json::Map<_, _>>>()
+        .map(serde_json::Value::Object)
+}
+
+/// [`EdgedbQueryArg::to_query_arg`] of a nested insert
+#[doc(hidden)]
+pub fn __insert_json<T: EdgedbInsert>(value: T) -> Result<Value> {
+    let val = serde_json::to_string(&json_object(value)?)?;
+
+    // safety: we just serialized this value
+    Ok(Value::Json(unsafe { Json::new_unchecked(val) }))
+}
+
+/// [`EdgedbInsert::format_json_insert`] of a nested insert
+#[doc(hidden)]
+pub fn __nested_insert<T: EdgedbInsert>(item: &str) -> String {
+    let mut buf = String::new();
+    T::format_json_insert(&mut buf, item).unwrap();
+    buf
+}
+
+/// The value of `name := ...` in an insert: from the `placeholder` parameter, or from the JSON
+/// object at `item`
+#[doc(hidden)]
+pub fn __insert_value<T: EdgedbQueryArg>(
+    name: &str,
+    link: Option<&str>,
+    placeholder: &str,
+    item: Option<&str>,
+) -> String {
+    let arg = match item {
+        Some(item) => format!("{item}['{name}']"),
+        None => crate::__query_arg::<T>(placeholder),
+    };
+
+    // `Vec`s and nested inserts are JSON, which can be `null` inside of another object
+    let json = match (item, T::OPTIONAL) {
+        (Some(_), true) => format!(
+            "(with {name}_json := {arg} select {name}_json filter json_typeof({name}_json) != 'null')"
+        ),
+        _ => arg.clone(),
+    };
+    let set = match T::MULTI {
+        true => format!("json_array_unpack({json})"),
+        false => json,
+    };
+    let scalar = match item {
+        Some(_) => format!("<{}>{arg}", T::ELEMENT_CAST),
+        None => arg,
+    };
+
+    match (T::NESTED_INSERT, T::MULTI, link) {
+        (Some(insert), _, _) => {
+            let var = format!("{name}_item");
+            format!(
+                "(for {var} in {{{set}}} union ({}))",
+                crate::__query_add_indent(&insert(&var))
+            )
+        }
+        (None, true, Some(target)) => {
+            format!("(select {target} filter .id in <uuid>{set})")
+        }
+        (None, true, None) => format!("<{}>{set}", T::ELEMENT_CAST),
+        (None, false, Some(target)) => format!("(select {target} filter .id = {scalar})"),
+        (None, false, None) => scalar,
+    }
+}
+
+/// Insert all of `values` in one query, and return their `id`s in the same order.
+///
+/// The values are passed as one `<json>$0` array, and each field is cast from it:
+/// `for entry in enumerate(json_array_unpack(<json>$0)) union (...)`, inserting
+/// `T { f := <str>item['f'], ... }` for each `item := entry.1`, and selecting its index with its `id`.
+/// The scalar fields have to be JSON-castable: strings, numbers, `bool`s and `uuid`s.
+pub async fn bulk_insert<T: EdgedbInsert>(
+    client: &Client,
+    values: impl IntoIterator<Item = T>,
+) -> Result<Vec<Uuid>> {
+    let items = values
+        .into_iter()
+        .map(json_object)
+        .collect::<Result<Vec<_>>>()?;
+
+    if items.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut ids: Vec<(i64, Uuid)> = crate::query(
+        client,
+        &T::bulk_query(),
+        (EdgedbJson(serde_json::Value::Array(items)),),
+    )
+    .await?;
+
+    ids.sort_by_key(|(index, _)| *index);
+
+    Ok(ids.into_iter().map(|(_, id)| id).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use edgedb_protocol::model::Uuid;
+
+    use super::{__insert_value, bulk_insert, run_insert, EdgedbInsert};
+    use crate::{
+        composable::{run_query, EdgedbComposableQuery, EdgedbComposableSelector},
+        EdgedbObject, Ref,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    struct InnerSelector {
+        id: Uuid,
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[derive(EdgedbInsert)]
+    #[edgedb(type = "Inner", returning = "InnerSelector")]
+    struct NewInner {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[derive(EdgedbInsert)]
+    #[edgedb(type = "Outer")]
+    struct NewOuter {
+        other_field: String,
+        inner: Option<Ref<InnerSelector>>,
+        #[edgedb(link = "Inner")]
+        a: Option<Uuid>,
+    }
+
+    #[derive(EdgedbInsert)]
+    #[edgedb(type = "Inner")]
+    struct NestedInner {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[derive(EdgedbInsert)]
+    #[edgedb(type = "Outer")]
+    struct NewAggregate {
+        other_field: String,
+        inner: NestedInner,
+        a: Option<NestedInner>,
+        b: Option<Ref<InnerSelector>>,
+    }
+
+    #[derive(
+        Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector, EdgedbComposableQuery,
+    )]
+    #[params(id: Uuid)]
+    #[select("select Outer filter .id = id limit 1")]
+    struct AggregateById {
+        inner: Option<InnerSelector>,
+        a: Option<InnerSelector>,
+    }
+
+    #[derive(EdgedbInsert)]
+    #[edgedb(type = "Inner", returning = "InnerSelector")]
+    #[upsert(conflict_on = ".req")]
+    struct UpsertInner {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[test]
+    fn insert_queries() {
+        insta::assert_snapshot!(NewInner::query());
+        insta::assert_snapshot!(NewOuter::query());
+    }
+
+    #[test]
+    fn bulk_insert_queries() {
+        insta::assert_snapshot!(NewInner::bulk_query());
+        insta::assert_snapshot!(NewOuter::bulk_query());
+    }
+
+    #[test]
+    fn nested_insert_queries() {
+        insta::assert_snapshot!(NewAggregate::query());
+        insta::assert_snapshot!(NewAggregate::bulk_query());
+    }
+
+    #[test]
+    fn set_values() {
+        assert_eq!(
+            __insert_value::<Vec<String>>("tags", None, "$1", None),
+            "<str>json_array_unpack(<json>$1)"
+        );
+        assert_eq!(
+            __insert_value::<Vec<Ref<InnerSelector>>>("members", Some("Inner"), "$1", None),
+            "(select Inner filter .id in <uuid>json_array_unpack(<json>$1))"
+        );
+        assert_eq!(
+            __insert_value::<Option<Vec<NestedInner>>>("members", None, "", Some("item")),
+            "(for members_item in {json_array_unpack((with members_json := item['members'] \
+             select members_json filter json_typeof(members_json) != 'null'))} union (\
+             insert Inner {\n\
+             \t\treq := <str>members_item['req'],\n\
+             \t\topt := <str>members_item['opt'],\n\
+             \t}))"
+        );
+    }
+
+    #[test]
+    fn upsert_query() {
+        insta::assert_snapshot!(UpsertInner::query());
+    }
+
+    #[tokio::test]
+    async fn inserts() -> anyhow::Result<()> {
+        let conn = edgedb_tokio::create_client().await?;
+
+        let inner = run_insert(
+            &conn,
+            NewInner {
+                req: "inserted".to_string(),
+                opt: None,
+            },
+        )
+        .await?;
+
+        assert_eq!(inner.req, "inserted");
+        assert_eq!(inner.opt, None);
+
+        run_insert(
+            &conn,
+            NewOuter {
+                other_field: "inserted".to_string(),
+                inner: Some(Ref {
+                    id: inner.id,
+                    known_value: None,
+                }),
+                a: Some(inner.id),
+            },
+        )
+        .await?;
+
+        let aggregate = run_insert(
+            &conn,
+            NewAggregate {
+                other_field: "inserted".to_string(),
+                inner: NestedInner {
+                    req: "inserted".to_string(),
+                    opt: None,
+                },
+                a: None,
+                b: None,
+            },
+        )
+        .await?;
+
+        let ids = bulk_insert(
+            &conn,
+            [NewAggregate {
+                other_field: "inserted".to_string(),
+                inner: NestedInner {
+                    req: "inserted".to_string(),
+                    opt: Some("nested".to_string()),
+                },
+                a: Some(NestedInner {
+                    req: "inserted".to_string(),
+                    opt: None,
+                }),
+                b: None,
+            }],
+        )
+        .await?;
+
+        // the nested inserts are linked to the `Outer`s
+        let inserted = run_query::<AggregateById>(&conn, (aggregate,)).await?;
+        let inner = inserted.inner.unwrap();
+        assert_eq!((inner.req.as_str(), inner.opt), ("inserted", None));
+        assert!(inserted.a.is_none());
+
+        let inserted = run_query::<AggregateById>(&conn, (ids[0],)).await?;
+        let inner = inserted.inner.unwrap();
+        assert_eq!(
+            (inner.req.as_str(), inner.opt.as_deref()),
+            ("inserted", Some("nested"))
+        );
+        let a = inserted.a.unwrap();
+        assert_eq!((a.req.as_str(), a.opt), ("inserted", None));
+
+        let ids = bulk_insert(
+            &conn,
+            (0..5).map(|i| NewOuter {
+                other_field: "inserted".to_string(),
+                inner: None,
+                a: (i % 2 == 0).then_some(inner.id),
+            }),
+        )
+        .await?;
+
+        assert_eq!(ids.len(), 5);
+
+        // in the order of the values
+        for (i, id) in ids.iter().enumerate() {
+            let a = crate::query::<Option<Uuid>, _>(
+                &conn,
+                "select (select Outer filter .id = <uuid>$0).a.id",
+                (*id,),
+            )
+            .await?;
+
+            assert_eq!(a, (i % 2 == 0).then_some(inner.id));
+        }
+
+        conn.execute("delete Outer filter .other_field = 'inserted'", &())
+            .await?;
+        conn.execute("delete Inner filter .req = 'inserted'", &())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -96,15 +96,247 @@
 //! # anyhow::Ok(())
 //! # }).unwrap();
 //! ```
+//!
+//! # Filters, ordering and pagination
+//!
+//! Instead of baking them into the `#[select]` string, use `#[filter]` (repeated ones are
+//! joined with `and`), `#[order_by]` (joined with `then`), `#[offset]` and `#[limit]`.
+//! They're added after the shape, and can use the `#[params]` and `#[with]` names:
+//!
+//! ```
+//! use edgedb_composable_query::{EdgedbObject, composable::{EdgedbComposableQuery, EdgedbComposableSelector}};
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! struct InnerSelector {
+//!   req: String,
+//! }
+//!
+//! #[derive(EdgedbComposableQuery)]
+//! #[params(prefix: String, n: i64)]
+//! #[select("Inner")]
+//! #[filter(".req like prefix ++ '%'")]
+//! #[order_by(".req desc")]
+//! #[limit(n)]
+//! struct InnersByPrefix(Vec<InnerSelector>);
+//!
+//! assert!(InnersByPrefix::query().ends_with("\n}\nfilter .req like prefix ++ '%'\norder by .req desc\nlimit n"));
+//! ```
+//!
+//! Nested selectors take the same clauses per object with `#[nested(...)]`, written after
+//! their shape. `filter` and `order_by` can be repeated:
+//!
+//! ```
+//! use edgedb_composable_query::{EdgedbObject, composable::{EdgedbComposableQuery, EdgedbComposableSelector}};
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! struct InnerSelector {
+//!   req: String,
+//! }
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! struct OuterSelector {
+//!   #[nested(filter = "exists .opt", order_by = ".req desc", limit = 1)]
+//!   inner: Option<InnerSelector>,
+//! }
+//!
+//! #[derive(EdgedbComposableQuery)]
+//! #[select("Outer")]
+//! struct Outers(Vec<OuterSelector>);
+//!
+//! assert!(Outers::query().contains("\n\t}\n\tfilter exists .opt\n\torder by .req desc\n\tlimit 1"));
+//! ```
+//!
+//! Reverse links are `#[backlink(Type.link)]` fields, selected with their own shape and
+//! `#[nested(...)]` clauses: `#[backlink(Outer.inner)] outers: Vec<OuterSelector>` is
+//! `outers := (select .<inner[is Outer] { ... })`.
+//!
+//! Fields of a subtype, through a polymorphic link, are `#[is(Subtype)]` fields:
+//!
+//! ```
+//! use edgedb_composable_query::{EdgedbObject, composable::{EdgedbComposableQuery, EdgedbComposableSelector}};
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! struct ChapterSelector {
+//!   title: String,
+//! }
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector, EdgedbComposableQuery)]
+//! #[select("Content")]
+//! struct ContentSelector {
+//!   title: String,
+//!   #[is(Video)]
+//!   duration: Option<i64>,
+//!   #[is(Video)]
+//!   chapters: Vec<ChapterSelector>,
+//! }
+//!
+//! assert_eq!(
+//!     ContentSelector::query(),
+//!     "select (Content) {
+//! \ttitle,
+//! \tduration := [is Video].duration,
+//! \tchapters := (select [is Video].chapters {
+//! \t\ttitle := (.title),
+//! \t})
+//! }"
+//! );
+//! ```
+//!
+//! For wide selectors, `#[splat]` selects every property with `*` (or `**` with
+//! `#[splat(deep)]`) instead of listing them, and the nested selectors as usual. The result is
+//! decoded by name, so the struct can have `Option` fields the type doesn't:
+//!
+//! ```
+//! use edgedb_composable_query::{EdgedbObject, composable::{EdgedbComposableQuery, EdgedbComposableSelector}};
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! struct InnerSelector {
+//!   req: String,
+//! }
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! #[splat]
+//! struct OuterAdminView {
+//!   other_field: String,
+//!   some_field: Option<String>,
+//!   inner: Option<InnerSelector>,
+//! }
+//!
+//! #[derive(EdgedbComposableQuery)]
+//! #[select("Outer")]
+//! struct OuterAdminViews(Vec<OuterAdminView>);
+//!
+//! assert!(OuterAdminViews::query().ends_with(
+//!     "select (_selector) {\n\t*,\n\tinner: {\n\t\treq := (.req),\n\t}\n}"
+//! ));
+//! ```
+//!
+//! A selector can select itself, with `#[recursive(depth = N)]` on the field: it's nested `N`
+//! levels deep, then left out of the shape, so a `Vec` is decoded as empty and an `Option` as
+//! `None`. A [`Ref`] only selects its `id` past the depth.
+//!
+//! ```
+//! use edgedb_composable_query::{EdgedbObject, composable::{EdgedbComposableQuery, EdgedbComposableSelector}};
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! struct CategoryTree {
+//!   name: String,
+//!   #[recursive(depth = 2)]
+//!   children: Vec<CategoryTree>,
+//! }
+//!
+//! #[derive(EdgedbComposableQuery)]
+//! #[select("Category")]
+//! struct Categories(Vec<CategoryTree>);
+//!
+//! assert_eq!(
+//!     Categories::query(),
+//!     "with
+//! \t_selector := (Category),
+//! select (_selector) {
+//! \tname,
+//! \tchildren: {
+//! \t\tname,
+//! \t\tchildren: {
+//! \t\t\tname
+//! \t\t}
+//! \t}
+//! }"
+//! );
+//! ```
+//!
+//! Fields shared by several selectors can be a selector of their own, inlined into the
+//! shape with `#[flatten]`, and decoded into the nested struct:
+//!
+//! ```
+//! use edgedb_composable_query::{EdgedbObject, composable::EdgedbComposableSelector};
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! struct AuditFields {
+//!   some_field: Option<String>,
+//!   other_field: String,
+//! }
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! struct OuterSelector {
+//!   #[flatten]
+//!   audit: AuditFields,
+//! }
+//!
+//! let mut buf = String::new();
+//! OuterSelector::format_selector(&mut buf).unwrap();
+//!
+//! assert_eq!(buf, "\tsome_field := (.some_field),\n\tother_field := (.other_field),");
+//! ```
+//!
+//! # Schema checks
+//!
+//! With `#[edgedb(type = "...")]`, a selector's fields are checked at compile time against
+//! the `dbschema/*.esdl` files: property and link names, primitive types vs scalar types,
+//! and `Option`/`Vec`/`NonEmpty` vs `required`/`multi`.
+//!
+//! The schema directory is the closest `dbschema` up from the crate, or
+//! `#[edgedb(schema = "path/from/the/crate")]`, or the `EDGEDB_COMPOSABLE_SCHEMA_DIR` env var.
+//!
+//! ```compile_fail
+//! use edgedb_composable_query::{EdgedbObject, composable::EdgedbComposableSelector};
+//!
+//! #[derive(EdgedbObject, EdgedbComposableSelector)]
+//! #[edgedb(type = "Inner")]
+//! struct InnerSelector {
+//!   req: String,
+//!   opt: String, // `opt` is an optional property of `Inner`, expected `Option<_>`
+//! }
+//! ```
 
-use crate::{EdgedbObject, EdgedbPrim, EdgedbQueryArgs, EdgedbSetValue, Ref};
+use crate::{EdgedbObject, EdgedbPrim, EdgedbQueryArgs, EdgedbSetValue, IdOnly, QueryChecker, Ref};
 
 pub use edgedb_composable_query_derive::{EdgedbComposableQuery, EdgedbComposableSelector};
+pub use edgedb_protocol::server_message::Cardinality;
 use edgedb_tokio::Client;
 use nonempty::NonEmpty;
 
 use crate::Result;
 
+mod drift;
+pub use drift::{
+    diff_selectors, registered_selectors, schema_drift, DriftProblem, RegisteredSelector,
+    SchemaDrift, SelectorField, SelectorTarget,
+};
+
+mod aggregate;
+pub use aggregate::{Count, EdgedbSelectQuery, Exists};
+
+mod group;
+pub use group::Grouped;
+
+mod page;
+#[doc(hidden)]
+pub use page::__page_key_cast;
+pub use page::{run_page, Cursor, EdgedbPaginatedQuery, Page, Paginated};
+
+mod insert;
+#[doc(hidden)]
+pub use insert::{__insert_json, __insert_value, __nested_insert};
+pub use insert::{bulk_insert, run_insert, EdgedbInsert};
+
+mod update;
+#[doc(hidden)]
+pub use update::{__delete_filter, __update_set};
+pub use update::{
+    run_delete, run_update, Add, EdgedbDelete, EdgedbUpdate, LinkOp, LinkOperator, Patch, Remove,
+    Replace,
+};
+
+mod recursive;
+#[doc(hidden)]
+pub use recursive::{__Cutoff, __recursive, __recursive_field_check};
+
+mod registry;
+pub use registry::{
+    export_queries, registered_queries, validate_all, QueryValidationError, RegisteredQuery,
+};
+
 pub enum ComposableQueryResultKind {
     Field,
     Selector,
@@ -112,9 +344,28 @@ pub enum ComposableQueryResultKind {
 }
 
 /// Derivable trait. Must have named fields, each is either another selector, or a primitive, or a `Vec/Option/NonEmpty` of those.
+///
+/// Add `#[edgedb(type = "TypeName")]` to check the fields against the schema at compile time
+/// (see the [module docs](self#schema-checks)), and to compare them against the live
+/// schema with [`schema_drift`].
 pub trait EdgedbComposableSelector {
     const RESULT_TYPE: ComposableQueryResultKind;
 
+    /// What this selects when it's a field, see [`schema_drift`]
+    const TARGET: SelectorTarget = SelectorTarget::Object(None);
+
+    /// `One`, or the cardinality of `Option`, `Vec` and `NonEmpty`
+    const CARDINALITY: Cardinality = Cardinality::One;
+
+    /// The shape a `#[recursive]` field selects past its depth, instead of leaving it out:
+    /// the `id` of a [`Ref`]
+    const CUTOFF: Option<&'static str> = None;
+
+    /// Fields selected from the type, without the computed ones
+    fn fields() -> Vec<SelectorField> {
+        Vec::new()
+    }
+
     /// should't add `{` and `}` around the selector
     fn format_selector(fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error>;
 
@@ -135,6 +386,7 @@ pub trait EdgedbComposableSelector {
 
 impl<T: EdgedbPrim> EdgedbComposableSelector for T {
     const RESULT_TYPE: ComposableQueryResultKind = ComposableQueryResultKind::Field;
+    const TARGET: SelectorTarget = SelectorTarget::Scalar(T::TYPE_CAST);
 
     fn format_selector(fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error> {
         Ok(())
@@ -147,30 +399,61 @@ impl<T: EdgedbPrim> EdgedbComposableSelector for T {
 
 impl<T: EdgedbComposableSelector> EdgedbComposableSelector for Vec<T> {
     const RESULT_TYPE: ComposableQueryResultKind = T::RESULT_TYPE;
+    const TARGET: SelectorTarget = T::TARGET;
+    const CARDINALITY: Cardinality = Cardinality::Many;
+    const CUTOFF: Option<&'static str> = T::CUTOFF;
 
     fn format_selector(fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error> {
         T::format_selector(fmt)
     }
+
+    fn fields() -> Vec<SelectorField> {
+        T::fields()
+    }
 }
 
 impl<T: EdgedbComposableSelector> EdgedbComposableSelector for Option<T> {
     const RESULT_TYPE: ComposableQueryResultKind = T::RESULT_TYPE;
+    const TARGET: SelectorTarget = T::TARGET;
+    const CARDINALITY: Cardinality = Cardinality::AtMostOne;
+    const CUTOFF: Option<&'static str> = T::CUTOFF;
 
     fn format_selector(fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error> {
         T::format_selector(fmt)
     }
+
+    fn fields() -> Vec<SelectorField> {
+        T::fields()
+    }
 }
 
 impl<T: EdgedbComposableSelector> EdgedbComposableSelector for NonEmpty<T> {
     const RESULT_TYPE: ComposableQueryResultKind = T::RESULT_TYPE;
+    const TARGET: SelectorTarget = T::TARGET;
+    const CARDINALITY: Cardinality = Cardinality::AtLeastOne;
+    const CUTOFF: Option<&'static str> = T::CUTOFF;
 
     fn format_selector(fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error> {
         T::format_selector(fmt)
     }
+
+    fn fields() -> Vec<SelectorField> {
+        T::fields()
+    }
+}
+
+impl EdgedbComposableSelector for IdOnly {
+    const RESULT_TYPE: ComposableQueryResultKind = ComposableQueryResultKind::Selector;
+
+    fn format_selector(_fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error> {
+        Ok(())
+    }
 }
 
 impl<T: EdgedbComposableSelector + EdgedbObject> EdgedbComposableSelector for Ref<T> {
     const RESULT_TYPE: ComposableQueryResultKind = ComposableQueryResultKind::Selector;
+    const TARGET: SelectorTarget = T::TARGET;
+    const CUTOFF: Option<&'static str> = Some("\tid");
 
     fn format_selector(fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error> {
         fmt.write_str("\tid,\n")?;
@@ -179,6 +462,10 @@ impl<T: EdgedbComposableSelector + EdgedbObject> EdgedbComposableSelector for Re
 
         Ok(())
     }
+
+    fn fields() -> Vec<SelectorField> {
+        T::fields()
+    }
 }
 
 /// Derivable trait. Can have parameters. Either an object with named fields, or can be a wrapper around a selector, or `Option<selector>`, or `Vec<selector>`, or `NonEmpty<selector>``.
@@ -223,12 +510,63 @@ where
     crate::query(client, &query_s, args).await
 }
 
+/// Like [`run_query`], but prepares the query and checks its result type before executing it.
+pub async fn run_query_checked<T: EdgedbComposableQuery>(
+    client: &Client,
+    checker: &QueryChecker,
+    args: T::ArgTypes,
+) -> Result<T::ReturnType>
+where
+    <T as EdgedbComposableQuery>::ArgTypes: Send,
+{
+    let query_s = T::query();
+
+    crate::query_checked(client, checker, &query_s, args).await
+}
+
+/// Prepare the query on the server, and check its cardinality and type against [`EdgedbComposableQuery::ReturnType`].
+pub async fn check_query<T: EdgedbComposableQuery>(checker: &QueryChecker) -> Result<()> {
+    checker.check::<T::ReturnType>(&T::query()).await
+}
+
+/// Test helper: panics if [`check_query`] fails, using the environment's database.
+///
+/// ```no_run
+/// # use edgedb_composable_query::composable::{assert_query_checks, EdgedbComposableQuery};
+/// # use edgedb_composable_query::{EdgedbObject, composable::EdgedbComposableSelector};
+/// #[derive(EdgedbObject, EdgedbComposableSelector, EdgedbComposableQuery)]
+/// #[select("select Inner limit 1")]
+/// struct OneInner {
+///     req: String,
+/// }
+///
+/// #[tokio::test]
+/// async fn one_inner_query() {
+///     assert_query_checks::<OneInner>().await;
+/// }
+/// ```
+pub async fn assert_query_checks<T: EdgedbComposableQuery>() {
+    let checker = QueryChecker::from_env()
+        .await
+        .expect("couldn't connect to check the query");
+
+    if let Err(e) = check_query::<T>(&checker).await {
+        panic!(
+            "query {} doesn't match its return type: {e:#}\n{}",
+            std::any::type_name::<T>(),
+            T::query()
+        );
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use edgedb_protocol::model::Uuid;
+    use edgedb_protocol::{model::Uuid, value::Value};
 
+    use crate::composable::check_query;
     use crate::composable::EdgedbComposableQuery;
     use crate::composable::EdgedbComposableSelector;
+    use crate::shape::test_shape;
     use crate::{EdgedbObject, Ref};
 
     #[derive(
@@ -241,6 +579,7 @@ mod test {
     }
 
     #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Inner")]
     struct InnerSelector {
         req: String,
         opt: Option<String>,
@@ -259,10 +598,157 @@ mod test {
     #[select("select Inner limit 10")]
     struct ManyInnersBySelector(Vec<InnerSelector>);
 
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[params(req: String)]
+    #[select(file = "src/composable/test_queries/inners_by_req.edgeql")]
+    struct InnersByReqFromFile(Vec<InnerSelector>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[with(req = "'a'")]
+    #[direct(file = "src/composable/test_queries/inners_by_req.edgeql")]
+    struct DirectFromFile(Vec<InnerSelector>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[params(n: i64)]
+    #[select("Inner")]
+    #[filter("exists .opt")]
+    #[filter(".req != ''")]
+    #[order_by(".req")]
+    #[limit(n)]
+    struct FirstInnersWithOpt(Vec<InnerSelector>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    struct OuterWithOptInner {
+        other_field: String,
+
+        #[nested(
+            filter = "exists .opt",
+            filter = ".req != ''",
+            order_by = ".req",
+            limit = 1
+        )]
+        inner: Option<InnerSelector>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[select("Outer")]
+    struct OutersWithOptInner(Vec<OuterWithOptInner>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Outer")]
+    struct OuterFieldSelector {
+        other_field: String,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Inner")]
+    struct InnerWithOuters {
+        req: String,
+
+        #[backlink(Outer.inner)]
+        #[nested(order_by = ".other_field", limit = 5)]
+        outers: Vec<OuterFieldSelector>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[select("Inner")]
+    struct InnersWithOuters(Vec<InnerWithOuters>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[splat]
+    struct OuterAdminView {
+        other_field: String,
+        some_field: Option<String>,
+        /// not a property of `Outer`, always `None`
+        extra_field: Option<String>,
+
+        inner: Option<InnerSelector>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[select("Outer")]
+    struct OuterAdminViews(Vec<OuterAdminView>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[splat(deep)]
+    struct DeepOuterSelector {
+        other_field: String,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Content")]
+    struct ContentSelector {
+        title: String,
+
+        #[is(Video)]
+        duration: Option<i64>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Content")]
+    struct ContentWithRelated {
+        title: String,
+
+        #[nested(order_by = ".title")]
+        related: Vec<ContentSelector>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[params(id: Uuid)]
+    #[select("select Content filter .id = id")]
+    struct ContentById(Option<ContentWithRelated>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Inner")]
+    struct InnerTree {
+        req: String,
+
+        #[backlink(Outer.inner)]
+        #[recursive(depth = 2)]
+        outers: Vec<OuterTree>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Outer")]
+    struct OuterTree {
+        other_field: String,
+
+        #[recursive(depth = 1)]
+        inner: Option<Ref<InnerTree>>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[select("Inner")]
+    struct InnerTrees(Vec<InnerTree>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[select("Outer")]
+    struct OuterTrees(Vec<OuterTree>);
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Outer")]
+    struct OuterFields {
+        some_field: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    #[edgedb(type = "Outer")]
+    struct OuterWithFlattened {
+        other_field: String,
+
+        #[flatten]
+        fields: OuterFields,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+    #[select("Outer")]
+    struct OutersWithFlattened(Vec<OuterWithFlattened>);
+
     #[derive(
         Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector, EdgedbComposableQuery,
     )]
     #[select("select Outer limit 1")]
+    #[edgedb(type = "Outer")]
     struct OuterQuery {
         inner: Option<InnerSelector>,
 
@@ -314,4 +800,234 @@ mod test {
         insta::assert_snapshot!(OneInnerBySelectorById::query());
         insta::assert_snapshot!(ManyInnersBySelector::query());
     }
+
+    #[test]
+    fn file_query_tests() {
+        insta::assert_snapshot!(InnersByReqFromFile::query());
+        insta::assert_snapshot!(DirectFromFile::query());
+    }
+
+    #[test]
+    fn clause_query_tests() {
+        insta::assert_snapshot!(FirstInnersWithOpt::query());
+    }
+
+    #[test]
+    fn nested_clause_query_tests() {
+        insta::assert_snapshot!(OutersWithOptInner::query());
+    }
+
+    #[test]
+    fn backlink_query_tests() {
+        insta::assert_snapshot!(InnersWithOuters::query());
+    }
+
+    #[test]
+    fn type_intersection_query_tests() {
+        insta::assert_snapshot!(ContentById::query());
+    }
+
+    #[test]
+    fn splat_query_tests() {
+        insta::assert_snapshot!(OuterAdminViews::query());
+
+        let mut buf = String::new();
+        DeepOuterSelector::format_selector(&mut buf).unwrap();
+        assert_eq!(buf, "\t**");
+    }
+
+    #[test]
+    fn splat_decode_tests() -> anyhow::Result<()> {
+        // `*` selects every property, and the link
+        let outer = OuterAdminView::from_edgedb_object(
+            test_shape(&["id", "other_field", "some_field", "inner"]),
+            vec![
+                Some(Value::Uuid(Uuid::from_u128(1))),
+                Some(Value::Str("other".to_string())),
+                None,
+                None,
+            ],
+        )?;
+
+        assert_eq!(
+            outer,
+            OuterAdminView {
+                other_field: "other".to_string(),
+                some_field: None,
+                extra_field: None,
+                inner: None,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_query_tests() {
+        insta::assert_snapshot!(InnerTrees::query());
+        insta::assert_snapshot!(OuterTrees::query());
+    }
+
+    #[test]
+    fn recursive_decode_tests() -> anyhow::Result<()> {
+        // the last level, without `outers`
+        let inner = InnerTree::from_edgedb_object(
+            test_shape(&["req"]),
+            vec![Some(Value::Str("req".to_string()))],
+        )?;
+
+        assert_eq!(
+            inner,
+            InnerTree {
+                req: "req".to_string(),
+                outers: vec![],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn flatten_query_tests() {
+        insta::assert_snapshot!(OutersWithFlattened::query());
+    }
+
+    #[test]
+    fn flatten_decode_tests() -> anyhow::Result<()> {
+        let shape = test_shape(&["some_field", "other_field"]);
+
+        let outer = OuterWithFlattened::from_edgedb_object(
+            shape,
+            vec![
+                Some(Value::Str("some".to_string())),
+                Some(Value::Str("other".to_string())),
+            ],
+        )?;
+
+        assert_eq!(
+            outer,
+            OuterWithFlattened {
+                other_field: "other".to_string(),
+                fields: OuterFields {
+                    some_field: Some("some".to_string()),
+                },
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn registry_tests() {
+        let names = crate::composable::registered_queries()
+            .into_iter()
+            .map(|q| q.type_name)
+            .collect::<Vec<_>>();
+
+        assert!(names.contains(&"edgedb_composable_query::composable::test::InnerQuery"));
+        assert!(names.contains(&"edgedb_composable_query::composable::test::ManyInnersBySelector"));
+
+        let by_id = crate::composable::registered_queries()
+            .into_iter()
+            .find(|q| q.short_name() == "OneInnerBySelectorById")
+            .unwrap();
+        insta::assert_snapshot!(by_id.to_edgeql_file());
+
+        // args without `type_casts` are listed by name only
+        let mut without_casts = crate::composable::RegisteredQuery::new::<OneInnerBySelectorById>(
+            "OneInnerBySelectorById",
+        );
+        without_casts.arg_type_casts = Vec::new;
+        assert!(without_casts
+            .to_edgeql_file()
+            .contains("# parameters:\n#   $0 id\n#\n"));
+    }
+
+    #[tokio::test]
+    async fn validate_all_queries() -> anyhow::Result<()> {
+        let checker = crate::QueryChecker::from_env().await?;
+
+        let errors = crate::composable::validate_all(&checker).await;
+
+        // the other test modules register their queries too
+        let failing = errors
+            .iter()
+            .map(|e| e.type_name)
+            .filter(|name| name.starts_with(concat!(module_path!(), "::")))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            failing,
+            vec![std::any::type_name::<OneInnerBySelectorById>()]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checked_queries() -> anyhow::Result<()> {
+        let checker = crate::QueryChecker::from_env().await?;
+
+        check_query::<InnerQuery>(&checker).await?;
+        check_query::<OuterQueryWithRef>(&checker).await?;
+        check_query::<ManyInnersBySelector>(&checker).await?;
+        check_query::<FirstInnersWithOpt>(&checker).await?;
+        check_query::<OutersWithOptInner>(&checker).await?;
+        check_query::<InnersWithOuters>(&checker).await?;
+        check_query::<ContentById>(&checker).await?;
+
+        // `filter .id = id` is AT_MOST_ONE
+        assert!(check_query::<OneInnerBySelectorById>(&checker)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn polymorphic_links() -> anyhow::Result<()> {
+        let conn = edgedb_tokio::create_client().await?;
+
+        let id: Uuid = crate::query(
+            &conn,
+            "select (insert Video {
+                title := 'with related',
+                related := {
+                    (insert Video { title := 'a video', duration := 60 }),
+                    (insert Article { title := 'an article' }),
+                },
+            }).id",
+            (),
+        )
+        .await?;
+
+        let content = crate::composable::run_query::<ContentById>(&conn, (id,)).await?;
+
+        // both in one statement, the links to `related` would block deleting it first
+        conn.execute(
+            "with root := (select Content filter .id = <uuid>$0) delete (root union root.related)",
+            &(id,),
+        )
+        .await?;
+
+        // `related` has both subtypes, `duration` is only there for videos
+        assert_eq!(
+            content,
+            Some(ContentWithRelated {
+                title: "with related".to_string(),
+                related: vec![
+                    ContentSelector {
+                        title: "a video".to_string(),
+                        duration: Some(60),
+                    },
+                    ContentSelector {
+                        title: "an article".to_string(),
+                        duration: None,
+                    },
+                ],
+            })
+        );
+
+        Ok(())
+    }
 }
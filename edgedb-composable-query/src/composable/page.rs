@@ -0,0 +1,266 @@
+use std::marker::PhantomData;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use edgedb_protocol::{model::Json, value::Value};
+use edgedb_tokio::Client;
+
+use crate::{args::json_of, EdgedbQueryArg, EdgedbQueryArgs, EdgedbValue, Result};
+
+use super::{run_query, EdgedbComposableQuery, EdgedbSelectQuery};
+
+/// An opaque position in a [`Paginated`] query: the key of the last item of a page, encoded.
+/// Pass it back as `after` to [`run_page`] to get the next page.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cursor(String);
+
+impl Cursor {
+    /// The cursor after an item with this key
+    pub fn of<K: EdgedbQueryArg>(key: K) -> Result<Self> {
+        let json = serde_json::to_string(&json_of(key.to_query_arg()?)?)?;
+
+        Ok(Self(URL_SAFE_NO_PAD.encode(json)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Cursor {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Passed as the `<json>` of the key, and cast to the key's type in the query
+impl EdgedbQueryArg for Cursor {
+    const TYPE_CAST: &'static str = "json";
+
+    fn to_query_arg(self) -> Result<Value> {
+        let json = URL_SAFE_NO_PAD
+            .decode(&self.0)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .filter(|json| serde_json::from_str::<serde_json::Value>(json).is_ok())
+            .ok_or_else(|| anyhow::anyhow!("invalid cursor: {}", self.0))?;
+
+        // safety: we just parsed this value
+        Ok(Value::Json(unsafe { Json::new_unchecked(json) }))
+    }
+}
+
+/// One page of a [`Paginated`] query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The cursor after the last item, if there are more
+    pub next_cursor: Option<Cursor>,
+    pub has_more: bool,
+}
+
+/// Derived for an [`EdgedbComposableQuery`] with `#[paginate(by = "field")]`: keyset
+/// pagination, ordered by a field of the selector. The `#[params]` stay the same, followed by
+/// the cursor and the page size, and the `#[filter]`s still apply.
+///
+/// Needs `#[select]` on a wrapper struct, and no `#[order_by]`, `#[offset]` or `#[limit]`.
+/// The field has to be selected as-is, and be unique for the pages not to skip items.
+///
+/// ```
+/// use edgedb_composable_query::{
+///     composable::{EdgedbComposableQuery, EdgedbComposableSelector, EdgedbPaginatedQuery},
+///     EdgedbObject,
+/// };
+///
+/// #[derive(EdgedbObject, EdgedbComposableSelector)]
+/// struct Inner {
+///     req: String,
+/// }
+///
+/// #[derive(EdgedbComposableQuery)]
+/// #[select("Inner")]
+/// #[paginate(by = "req")]
+/// struct InnersByReq(Vec<Inner>);
+///
+/// assert_eq!(
+///     InnersByReq::page_query(),
+///     "with
+/// \t_selector := (Inner),
+/// \t_cursor := <optional json>$0,
+/// \t_limit := <int64>$1,
+/// select (_selector) {
+/// \treq := (.req),
+/// }
+/// filter (not exists _cursor or .req > <str>_cursor)
+/// order by .req
+/// limit _limit + 1"
+/// );
+/// ```
+pub trait EdgedbPaginatedQuery: EdgedbSelectQuery {
+    type Item: EdgedbValue;
+    /// [`EdgedbComposableQuery::ArgTypes`], then `Option<Cursor>` and the page size
+    type PageArgs: EdgedbQueryArgs;
+
+    /// [`EdgedbComposableQuery::ARG_NAMES`], then `_cursor` and `_limit`
+    const PAGE_ARG_NAMES: &'static [&'static str];
+
+    fn page_args(args: Self::ArgTypes, after: Option<Cursor>, limit: i64) -> Self::PageArgs;
+
+    /// The cursor after this item
+    fn cursor_of(item: &Self::Item) -> Result<Cursor>;
+
+    /// `select ... filter ... and .key > _cursor order by .key limit _limit + 1`
+    fn format_page(
+        fmt: &mut impl std::fmt::Write,
+        args: &::std::collections::HashMap<&str, String>,
+    ) -> Result<(), std::fmt::Error>;
+
+    /// The query of [`Paginated<Self>`]
+    fn page_query() -> String {
+        Paginated::<Self>::query()
+    }
+}
+
+/// A page of a query, with the same params followed by the cursor and the page size. It selects
+/// one item more than the page size, to know if there are more: use [`run_page`] to get a [`Page`].
+pub struct Paginated<Q>(PhantomData<Q>);
+
+impl<Q: EdgedbPaginatedQuery> EdgedbComposableQuery for Paginated<Q> {
+    const ARG_NAMES: &'static [&'static str] = Q::PAGE_ARG_NAMES;
+
+    type ArgTypes = Q::PageArgs;
+    type ReturnType = Vec<Q::Item>;
+
+    fn format_query(
+        fmt: &mut impl std::fmt::Write,
+        args: &::std::collections::HashMap<&str, String>,
+    ) -> Result<(), std::fmt::Error> {
+        Q::format_page(fmt, args)
+    }
+}
+
+/// Run a page of `Q`: the first one without `after`, then the one after each
+/// [`Page::next_cursor`].
+pub async fn run_page<Q: EdgedbPaginatedQuery>(
+    client: &Client,
+    args: Q::ArgTypes,
+    after: Option<Cursor>,
+    limit: i64,
+) -> Result<Page<Q::Item>>
+where
+    Q::PageArgs: Send,
+{
+    let mut items = run_query::<Paginated<Q>>(client, Q::page_args(args, after, limit)).await?;
+
+    let has_more = items.len() as i64 > limit;
+    items.truncate(limit.max(0) as usize);
+
+    let next_cursor = match items.last() {
+        Some(last) if has_more => Some(Q::cursor_of(last)?),
+        _ => None,
+    };
+
+    Ok(Page {
+        items,
+        next_cursor,
+        has_more,
+    })
+}
+
+/// [`EdgedbQueryArg::TYPE_CAST`] of the key of [`EdgedbPaginatedQuery::Item`]
+#[doc(hidden)]
+pub fn __page_key_cast<I, K: EdgedbQueryArg>(_key: fn(&I) -> &K) -> &'static str {
+    K::TYPE_CAST
+}
+
+#[cfg(test)]
+mod test {
+    use edgedb_protocol::{model::Datetime, value::Value};
+
+    use super::{run_page, Cursor, EdgedbPaginatedQuery};
+    use crate::{
+        composable::{EdgedbComposableQuery, EdgedbComposableSelector},
+        EdgedbObject, EdgedbQueryArg,
+    };
+
+    #[derive(Debug, EdgedbObject, EdgedbComposableSelector)]
+    struct InnerSelector {
+        req: String,
+    }
+
+    #[allow(unused)]
+    #[derive(EdgedbComposableQuery)]
+    #[params(opt: String)]
+    #[select("Inner")]
+    #[filter(".opt = opt")]
+    #[paginate(by = "req")]
+    struct InnersByReq(Vec<InnerSelector>);
+
+    #[test]
+    fn page_query() {
+        insta::assert_snapshot!(InnersByReq::page_query());
+    }
+
+    #[test]
+    fn cursors() -> anyhow::Result<()> {
+        let cursor = Cursor::of("some req".to_string())?;
+
+        assert_eq!(
+            cursor.clone().to_query_arg()?,
+            Value::Json(unsafe {
+                edgedb_protocol::model::Json::new_unchecked("\"some req\"".into())
+            })
+        );
+        assert_eq!(Cursor::from(cursor.to_string()), cursor);
+        assert!(Cursor::from("not a cursor".to_string())
+            .to_query_arg()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn datetime_cursors() -> anyhow::Result<()> {
+        let created_at = Datetime::from_unix_micros(1_700_000_000_123_456);
+
+        assert_eq!(
+            Cursor::of(created_at)?.to_query_arg()?,
+            Value::Json(unsafe {
+                edgedb_protocol::model::Json::new_unchecked(
+                    "\"2023-11-14T22:13:20.123456Z\"".into(),
+                )
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pages() -> anyhow::Result<()> {
+        let conn = edgedb_tokio::create_client().await?;
+
+        let mut after = None;
+        let mut reqs = Vec::new();
+
+        loop {
+            let page = run_page::<InnersByReq>(&conn, ("hi".to_string(),), after, 2).await?;
+
+            assert!(page.items.len() <= 2);
+            reqs.extend(page.items.into_iter().map(|item| item.req));
+
+            match page.next_cursor {
+                Some(cursor) => after = Some(cursor),
+                None => break,
+            }
+        }
+
+        assert!(reqs.windows(2).all(|w| w[0] < w[1]));
+
+        Ok(())
+    }
+}
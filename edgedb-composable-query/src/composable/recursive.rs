@@ -0,0 +1,77 @@
+use std::{cell::Cell, marker::PhantomData, thread::LocalKey};
+
+use super::{ComposableQueryResultKind, EdgedbComposableSelector};
+
+/// Formats one level deeper into a `#[recursive(depth = N)]` field, or `None` past its depth.
+/// `level` is the field's own counter, so it only counts the levels of this field.
+#[doc(hidden)]
+pub fn __recursive<T>(
+    level: &'static LocalKey<Cell<usize>>,
+    depth: usize,
+    f: impl FnOnce() -> Result<T, std::fmt::Error>,
+) -> Result<Option<T>, std::fmt::Error> {
+    let current = level.get();
+
+    if current >= depth {
+        return Ok(None);
+    }
+
+    level.set(current + 1);
+    let res = f();
+    level.set(current);
+
+    res.map(Some)
+}
+
+/// What a `#[recursive]` field selects past its depth: the [`EdgedbComposableSelector::CUTOFF`]
+/// shape of `T`, like the `id` of a [`Ref`](crate::Ref)
+#[doc(hidden)]
+pub struct __Cutoff<T>(PhantomData<T>);
+
+impl<T: EdgedbComposableSelector> EdgedbComposableSelector for __Cutoff<T> {
+    const RESULT_TYPE: ComposableQueryResultKind = ComposableQueryResultKind::Selector;
+    const TARGET: super::SelectorTarget = T::TARGET;
+    const CARDINALITY: super::Cardinality = T::CARDINALITY;
+
+    fn format_selector(fmt: &mut impl std::fmt::Write) -> Result<(), std::fmt::Error> {
+        fmt.write_str(T::CUTOFF.unwrap_or_default())
+    }
+}
+
+/// `Vec` and `Option` fields are left out past the depth, and decoded as empty
+#[doc(hidden)]
+pub const fn __recursive_field_check<T: EdgedbComposableSelector>() {
+    assert!(
+        T::CUTOFF.is_some()
+            || matches!(
+                T::CARDINALITY,
+                super::Cardinality::Many | super::Cardinality::AtMostOne
+            ),
+        "expected a `Vec`, an `Option` or a `Ref` with #[recursive]: it's left out past the depth"
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use super::__recursive;
+
+    thread_local! {
+        static LEVEL: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn nested(depth: usize) -> Result<String, std::fmt::Error> {
+        Ok(
+            __recursive(&LEVEL, depth, || nested(depth).map(|s| format!("({s})")))?
+                .unwrap_or_default(),
+        )
+    }
+
+    #[test]
+    fn recursion_depth() {
+        assert_eq!(nested(0).unwrap(), "");
+        assert_eq!(nested(3).unwrap(), "((()))");
+        assert_eq!(LEVEL.get(), 0);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+use edgedb_protocol::server_message::Cardinality;
+
+use crate::{describe::cardinality_name, EdgedbQueryArgs, EdgedbSetValue, QueryChecker, Result};
+
+use super::{check_query, EdgedbComposableQuery};
+
+type CheckFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// An [`EdgedbComposableQuery`], registered automatically by its derive. See [`validate_all`].
+pub struct RegisteredQuery {
+    pub type_name: &'static str,
+    pub query: fn() -> String,
+    pub arg_names: &'static [&'static str],
+    pub arg_type_casts: fn() -> Vec<String>,
+    pub return_type_name: fn() -> &'static str,
+    pub return_cardinality: Cardinality,
+    check: for<'a> fn(&'a QueryChecker) -> CheckFuture<'a>,
+}
+
+inventory::collect!(RegisteredQuery);
+
+impl RegisteredQuery {
+    #[doc(hidden)]
+    pub const fn new<T: EdgedbComposableQuery + 'static>(type_name: &'static str) -> Self {
+        Self {
+            type_name,
+            query: T::query,
+            arg_names: T::ARG_NAMES,
+            arg_type_casts: <T::ArgTypes as EdgedbQueryArgs>::type_casts,
+            return_type_name: std::any::type_name::<T::ReturnType>,
+            return_cardinality: <T::ReturnType as EdgedbSetValue>::EXPECTED_CARDINALITY,
+            check: |checker| Box::pin(check_query::<T>(checker)),
+        }
+    }
+
+    /// See [`check_query`].
+    pub async fn check(&self, checker: &QueryChecker) -> Result<()> {
+        (self.check)(checker).await
+    }
+
+    /// Type name without the module path.
+    pub fn short_name(&self) -> &'static str {
+        self.type_name.rsplit("::").next().unwrap_or(self.type_name)
+    }
+
+    /// The query, with its parameters and expected result type in a header comment.
+    pub fn to_edgeql_file(&self) -> String {
+        let mut buf = format!("# {}\n#\n", self.type_name);
+
+        if self.arg_names.is_empty() {
+            buf.push_str("# parameters: none\n");
+        } else {
+            buf.push_str("# parameters:\n");
+
+            let casts = (self.arg_type_casts)();
+
+            for (i, name) in self.arg_names.iter().enumerate() {
+                match casts.get(i) {
+                    Some(cast) => buf.push_str(&format!("#   ${i} {name}: {cast}\n")),
+                    None => buf.push_str(&format!("#   ${i} {name}\n")),
+                }
+            }
+        }
+
+        buf.push_str(&format!(
+            "#\n# returns {}: {}\n\n{};\n",
+            cardinality_name(self.return_cardinality),
+            (self.return_type_name)(),
+            (self.query)()
+        ));
+
+        buf
+    }
+}
+
+/// All the queries derived in the binary, sorted by their type names.
+pub fn registered_queries() -> Vec<&'static RegisteredQuery> {
+    let mut queries = inventory::iter::<RegisteredQuery>
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    queries.sort_by_key(|q| q.type_name);
+
+    queries
+}
+
+/// Write every registered query to `<dir>/<TypeName>.edgeql` (see [`RegisteredQuery::to_edgeql_file`]),
+/// so they can be reviewed in diffs or analyzed with `edgedb` tooling. Returns the written paths.
+pub fn export_queries(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let queries = registered_queries();
+
+    // before writing anything, so a clash doesn't leave a half-exported directory
+    let mut seen = HashSet::new();
+
+    for registered in &queries {
+        if !seen.insert(registered.short_name()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "more than one registered query is named {}",
+                    registered.short_name()
+                ),
+            ));
+        }
+    }
+
+    std::fs::create_dir_all(dir)?;
+
+    let mut written = Vec::new();
+
+    for registered in queries {
+        let path = dir.join(format!("{}.edgeql", registered.short_name()));
+        std::fs::write(&path, registered.to_edgeql_file())?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+/// A registered query that failed to prepare, or doesn't match its return type.
+#[derive(Debug)]
+pub struct QueryValidationError {
+    pub type_name: &'static str,
+    pub query: String,
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for QueryValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:#}\n\t{}",
+            self.type_name,
+            self.error,
+            crate::__query_add_indent(&self.query)
+        )
+    }
+}
+
+/// Prepare every registered query on the server (without executing them), and report
+/// syntax errors, unknown properties, and cardinality or type mismatches.
+///
+/// Run it at startup, or in CI:
+///
+/// ```no_run
+/// # tokio_test::block_on(async {
+/// use edgedb_composable_query::{composable::validate_all, QueryChecker};
+///
+/// let checker = QueryChecker::from_env().await?;
+///
+/// for problem in validate_all(&checker).await {
+///     eprintln!("{problem}");
+/// }
+/// # anyhow::Ok(())
+/// # }).unwrap();
+/// ```
+pub async fn validate_all(checker: &QueryChecker) -> Vec<QueryValidationError> {
+    let mut errors = Vec::new();
+
+    for registered in registered_queries() {
+        if let Err(error) = registered.check(checker).await {
+            errors.push(QueryValidationError {
+                type_name: registered.type_name,
+                query: (registered.query)(),
+                error,
+            });
+        }
+    }
+
+    errors
+}
@@ -0,0 +1,389 @@
+use std::marker::PhantomData;
+
+use edgedb_protocol::value::Value;
+use edgedb_tokio::Client;
+
+use crate::{EdgedbQueryArg, Result};
+
+use super::{run_query, EdgedbComposableQuery};
+
+pub use edgedb_composable_query_derive::{EdgedbDelete, EdgedbUpdate};
+
+/// A field of an [`EdgedbUpdate`] that's only set when it's `Set`. Unlike `Option`,
+/// `Patch<Option<T>>` can also clear an optional property or link with `Set(None)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Patch<T> {
+    #[default]
+    Keep,
+    Set(T),
+}
+
+/// `Keep` is `{}`, `Set(None)` is `[]`, `Set(v)` is `[v]`
+impl<T: EdgedbQueryArg> EdgedbQueryArg for Patch<T> {
+    const TYPE_CAST: &'static str = T::TYPE_CAST;
+    const OPTIONAL: bool = true;
+    const PATCH: bool = true;
+
+    fn to_query_arg(self) -> Result<Value> {
+        match self {
+            Patch::Keep => Ok(Value::Nothing),
+            Patch::Set(v) => Ok(Value::Array(match v.to_query_arg()? {
+                Value::Nothing => vec![],
+                v => vec![v],
+            })),
+        }
+    }
+}
+
+/// `+=`, `-=` or `:=`, see [`LinkOp`]
+pub trait LinkOperator {
+    const OPERATOR: &'static str;
+}
+
+/// `+=`: add to the set
+pub struct Add;
+/// `-=`: remove from the set
+pub struct Remove;
+/// `:=`: replace the set
+pub struct Replace;
+
+impl LinkOperator for Add {
+    const OPERATOR: &'static str = "+=";
+}
+
+impl LinkOperator for Remove {
+    const OPERATOR: &'static str = "-=";
+}
+
+impl LinkOperator for Replace {
+    const OPERATOR: &'static str = ":=";
+}
+
+/// A multi link or property of an [`EdgedbUpdate`], updated with `Op`:
+/// `friends += (select User filter .id in array_unpack(<array<uuid>>$n))`.
+pub struct LinkOp<T, Op: LinkOperator = Replace> {
+    pub values: Vec<T>,
+    op: PhantomData<Op>,
+}
+
+impl<T, Op: LinkOperator> LinkOp<T, Op> {
+    pub fn new(values: Vec<T>) -> Self {
+        Self {
+            values,
+            op: PhantomData,
+        }
+    }
+}
+
+impl<T, Op: LinkOperator> From<Vec<T>> for LinkOp<T, Op> {
+    fn from(values: Vec<T>) -> Self {
+        Self::new(values)
+    }
+}
+
+impl<T: EdgedbQueryArg, Op: LinkOperator> EdgedbQueryArg for LinkOp<T, Op> {
+    const TYPE_CAST: &'static str = T::TYPE_CAST;
+    const LINK_OPERATOR: Option<&'static str> = Some(Op::OPERATOR);
+
+    fn to_query_arg(self) -> Result<Value> {
+        Ok(Value::Array(
+            self.values
+                .into_iter()
+                .map(EdgedbQueryArg::to_query_arg)
+                .collect::<Result<_>>()?,
+        ))
+    }
+}
+
+/// `(select Target filter .id = ...)` for links
+fn arg_value<T: EdgedbQueryArg>(link: Option<&str>, arg: &str) -> String {
+    match (link, T::PATCH || T::LINK_OPERATOR.is_some()) {
+        (Some(target), true) => format!("(select {target} filter .id in array_unpack({arg}))"),
+        (Some(target), false) => format!("(select {target} filter .id = {arg})"),
+        (None, true) => format!("array_unpack({arg})"),
+        (None, false) => arg.to_string(),
+    }
+}
+
+/// `name := ...` in `set { ... }`, keeping the old value for `Option::None` and `Patch::Keep`
+#[doc(hidden)]
+pub fn __update_set<T: EdgedbQueryArg>(
+    name: &str,
+    link: Option<&str>,
+    placeholder: &str,
+) -> String {
+    let arg = crate::__query_arg::<T>(placeholder);
+    let value = arg_value::<T>(link, &arg);
+
+    if let Some(operator) = T::LINK_OPERATOR {
+        return format!("{name} {operator} {value}");
+    }
+
+    match (T::PATCH, T::OPTIONAL) {
+        (true, _) => format!("{name} := ({value} if exists {arg} else .{name})"),
+        (false, true) => format!("{name} := ({value} ?? .{name})"),
+        (false, false) => format!("{name} := {value}"),
+    }
+}
+
+/// `.name = ...`, or always true for `Option::None` and `Patch::Keep`
+#[doc(hidden)]
+pub fn __delete_filter<T: EdgedbQueryArg>(
+    name: &str,
+    link: Option<&str>,
+    placeholder: &str,
+) -> String {
+    let arg = crate::__query_arg::<T>(placeholder);
+    let field = match link {
+        Some(_) => format!(".{name}.id"),
+        None => format!(".{name}"),
+    };
+    let value = match T::PATCH {
+        true => format!("array_unpack({arg})"),
+        false => arg.clone(),
+    };
+
+    match T::OPTIONAL {
+        true => format!("(not exists {arg} or {field} ?= {value})"),
+        false => format!("{field} = {value}"),
+    }
+}
+
+/// Derivable: `update` an object by its `id`, one parameter per field. Runs with [`run_update`].
+///
+/// - `#[edgedb(type = "Name")]`: the type to update, required
+/// - `id`: the object to update
+/// - `Option` and [`Patch`] fields are only set when they're `Some`/`Set`
+/// - `Ref<T>` fields, and `Uuid` fields with `#[edgedb(link = "Target")]`, are links. A `Ref<T>`'s
+///   target comes from the schema, or `#[edgedb(link = "Target")]` if it isn't there
+/// - [`LinkOp`] fields add to, remove from, or replace multi links and properties
+/// - returns how many objects were updated, or `#[edgedb(returning = "Option<Selector>")]`
+///
+/// ```
+/// use edgedb_composable_query::composable::{EdgedbComposableQuery, EdgedbUpdate, Patch};
+/// use edgedb_protocol::model::Uuid;
+///
+/// #[derive(EdgedbUpdate)]
+/// #[edgedb(type = "Outer")]
+/// struct UpdateOuter {
+///     id: Uuid,
+///     other_field: String,
+///     some_field: Patch<Option<String>>,
+/// }
+///
+/// assert_eq!(
+///     UpdateOuter::query(),
+///     "select count((update Outer filter .id = <uuid>$0 set {
+/// \tother_field := <str>$1,
+/// \tsome_field := (array_unpack(<optional array<str>>$2) if exists <optional array<str>>$2 else .some_field),
+/// }))"
+/// );
+/// ```
+///
+/// ```
+/// use edgedb_composable_query::{
+///     composable::{Add, EdgedbComposableQuery, EdgedbUpdate, LinkOp, Remove},
+///     EdgedbObject, Ref,
+/// };
+/// use edgedb_protocol::model::Uuid;
+///
+/// #[derive(EdgedbObject)]
+/// struct User {
+///     id: Uuid,
+/// }
+///
+/// #[derive(EdgedbUpdate)]
+/// #[edgedb(type = "User")]
+/// struct Befriend {
+///     id: Uuid,
+///     #[edgedb(link = "User")]
+///     friends: LinkOp<Ref<User>, Add>,
+///     #[edgedb(link = "User")]
+///     blocked: LinkOp<Ref<User>, Remove>,
+/// }
+///
+/// assert_eq!(
+///     Befriend::query(),
+///     "select count((update User filter .id = <uuid>$0 set {
+/// \tfriends += (select User filter .id in array_unpack(<array<uuid>>$1)),
+/// \tblocked -= (select User filter .id in array_unpack(<array<uuid>>$2)),
+/// }))"
+/// );
+/// ```
+pub trait EdgedbUpdate: EdgedbComposableQuery {
+    /// The fields, in the order of [`EdgedbComposableQuery::ARG_NAMES`]
+    fn into_args(self) -> Self::ArgTypes;
+}
+
+/// Derivable: `delete` the objects whose properties and links equal the fields. `Option` fields
+/// only filter when they're `Some`, so at least one field has to be required, or all of them
+/// `None` would delete everything. Runs with [`run_delete`].
+///
+/// Returns how many objects were deleted, or `#[edgedb(returning = "Vec<Selector>")]`.
+///
+/// ```
+/// use edgedb_composable_query::composable::{EdgedbComposableQuery, EdgedbDelete};
+///
+/// #[derive(EdgedbDelete)]
+/// #[edgedb(type = "Inner")]
+/// struct DeleteInners {
+///     req: String,
+///     opt: Option<String>,
+/// }
+///
+/// assert_eq!(
+///     DeleteInners::query(),
+///     "select count((delete Inner filter .req = <str>$0 \
+///      and (not exists <optional str>$1 or .opt ?= <optional str>$1)))"
+/// );
+/// ```
+pub trait EdgedbDelete: EdgedbComposableQuery {
+    /// The fields, in the order of [`EdgedbComposableQuery::ARG_NAMES`]
+    fn into_args(self) -> Self::ArgTypes;
+}
+
+/// Update the object, and return the count or the `returning` selector.
+pub async fn run_update<T: EdgedbUpdate>(client: &Client, value: T) -> Result<T::ReturnType>
+where
+    <T as EdgedbComposableQuery>::ArgTypes: Send,
+{
+    run_query::<T>(client, value.into_args()).await
+}
+
+/// Delete the matching objects, and return the count or the `returning` selector.
+pub async fn run_delete<T: EdgedbDelete>(client: &Client, value: T) -> Result<T::ReturnType>
+where
+    <T as EdgedbComposableQuery>::ArgTypes: Send,
+{
+    run_query::<T>(client, value.into_args()).await
+}
+
+#[cfg(test)]
+mod test {
+    use edgedb_protocol::model::Uuid;
+
+    use super::{
+        __update_set, run_delete, run_update, EdgedbDelete, EdgedbUpdate, LinkOp, Patch, Remove,
+    };
+    use crate::{
+        composable::{run_insert, EdgedbComposableQuery, EdgedbComposableSelector, EdgedbInsert},
+        EdgedbObject, Ref,
+    };
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    struct InnerSelector {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+    struct OuterSelector {
+        other_field: String,
+    }
+
+    #[derive(EdgedbInsert)]
+    #[edgedb(type = "Inner")]
+    struct NewInner {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[derive(EdgedbUpdate)]
+    #[edgedb(type = "Inner", returning = "Option<InnerSelector>")]
+    struct UpdateInner {
+        id: Uuid,
+        req: Option<String>,
+        opt: Patch<Option<String>>,
+    }
+
+    #[derive(EdgedbUpdate)]
+    #[edgedb(type = "Outer")]
+    struct UpdateOuter {
+        id: Uuid,
+        other_field: String,
+        inner: Patch<Option<Ref<InnerSelector>>>,
+        a: Option<Ref<InnerSelector>>,
+    }
+
+    #[derive(EdgedbDelete)]
+    #[edgedb(type = "Inner")]
+    struct DeleteInners {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[derive(EdgedbDelete)]
+    #[edgedb(type = "Outer", returning = "Vec<OuterSelector>")]
+    struct DeleteOuters {
+        inner: Ref<InnerSelector>,
+    }
+
+    #[test]
+    fn update_queries() {
+        insta::assert_snapshot!(UpdateInner::query());
+        insta::assert_snapshot!(UpdateOuter::query());
+    }
+
+    #[test]
+    fn link_operators() {
+        assert_eq!(
+            __update_set::<LinkOp<Ref<InnerSelector>>>("inners", Some("Inner"), "$1"),
+            "inners := (select Inner filter .id in array_unpack(<array<uuid>>$1))"
+        );
+        assert_eq!(
+            __update_set::<LinkOp<String, Remove>>("tags", None, "$2"),
+            "tags -= array_unpack(<array<str>>$2)"
+        );
+    }
+
+    #[test]
+    fn delete_queries() {
+        insta::assert_snapshot!(DeleteInners::query());
+        insta::assert_snapshot!(DeleteOuters::query());
+    }
+
+    #[tokio::test]
+    async fn updates_and_deletes() -> anyhow::Result<()> {
+        let conn = edgedb_tokio::create_client().await?;
+
+        let id = run_insert(
+            &conn,
+            NewInner {
+                req: "to update".to_string(),
+                opt: Some("opt".to_string()),
+            },
+        )
+        .await?;
+
+        let updated = run_update(
+            &conn,
+            UpdateInner {
+                id,
+                req: None,
+                opt: Patch::Set(None),
+            },
+        )
+        .await?;
+
+        assert_eq!(
+            updated,
+            Some(InnerSelector {
+                req: "to update".to_string(),
+                opt: None,
+            })
+        );
+
+        let deleted = run_delete(
+            &conn,
+            DeleteInners {
+                req: "to update".to_string(),
+                opt: None,
+            },
+        )
+        .await?;
+
+        assert_eq!(deleted, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+use std::sync::Arc;
+
+use edgedb_protocol::{
+    codec,
+    common::{Capabilities, CompilationOptions, IoFormat},
+    descriptors::{Descriptor, TypePos, Typedesc},
+    model::Uuid,
+    server_message::Cardinality,
+};
+use edgedb_tokio::{
+    raw::{Pool, PoolState},
+    Builder, Client, Config,
+};
+
+use crate::{EdgedbQueryArgs, EdgedbSetValue, Result};
+
+/// Prepares queries on the server without executing them, to compare what they return
+/// against the Rust types they're going to be decoded into.
+///
+/// ```no_run
+/// # tokio_test::block_on(async {
+/// use edgedb_composable_query::{EdgedbObject, QueryChecker};
+///
+/// #[derive(EdgedbObject)]
+/// struct Inner {
+///     req: String,
+///     opt: Option<String>,
+/// }
+///
+/// let checker = QueryChecker::from_env().await?;
+///
+/// assert!(checker.check::<Vec<Inner>>("select Inner {req, opt}").await.is_ok());
+///
+/// // query returns MANY but Rust type `Inner` requires ONE
+/// assert!(checker.check::<Inner>("select Inner {req, opt}").await.is_err());
+/// # anyhow::Ok(())
+/// # }).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueryChecker {
+    pool: Pool,
+    state: Arc<PoolState>,
+}
+
+/// What the server inferred about a query's result.
+#[derive(Debug)]
+pub struct QueryDescription {
+    pub cardinality: Cardinality,
+    pub output: Typedesc,
+}
+
+impl QueryChecker {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            pool: Pool::new(config),
+            state: Arc::new(PoolState::default()),
+        }
+    }
+
+    /// Same configuration as [`edgedb_tokio::create_client`].
+    pub async fn from_env() -> Result<Self> {
+        Ok(Self::new(&Builder::new().build_env().await?))
+    }
+
+    /// Prepares the query, doesn't execute it.
+    pub async fn describe(&self, q: &str) -> Result<QueryDescription> {
+        let flags = CompilationOptions {
+            implicit_limit: None,
+            implicit_typenames: false,
+            implicit_typeids: false,
+            allow_capabilities: Capabilities::ALL,
+            explicit_objectids: true,
+            io_format: IoFormat::Binary,
+            expected_cardinality: Cardinality::Many,
+        };
+
+        let mut conn = self.pool.acquire().await?;
+        let desc = conn.parse(&flags, q, &self.state).await?;
+
+        Ok(QueryDescription {
+            cardinality: desc.result_cardinality,
+            output: desc.output()?,
+        })
+    }
+
+    /// Prepares the query and checks that its result can be decoded as `T`.
+    pub async fn check<T: EdgedbSetValue>(&self, q: &str) -> Result<()> {
+        self.describe(q).await?.check::<T>()
+    }
+}
+
+impl QueryDescription {
+    /// Checks the result cardinality and type against `T`.
+    pub fn check<T: EdgedbSetValue>(&self) -> Result<()> {
+        if !cardinality_fits(T::EXPECTED_CARDINALITY, self.cardinality) {
+            anyhow::bail!(
+                "query returns {} but Rust type `{}` requires {}",
+                cardinality_name(self.cardinality),
+                std::any::type_name::<T>(),
+                cardinality_name(T::EXPECTED_CARDINALITY),
+            );
+        }
+
+        match self.output.root_pos() {
+            Some(pos) => T::check_element_descriptor(&self.output, pos).map_err(|e| {
+                e.context(format!(
+                    "query result doesn't match Rust type `{}`",
+                    std::any::type_name::<T>()
+                ))
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Like [`crate::query`], but prepares the query and checks its result type before executing it.
+pub async fn query_checked<T: EdgedbSetValue, Args: EdgedbQueryArgs + Send>(
+    client: &Client,
+    checker: &QueryChecker,
+    q: &str,
+    args: Args,
+) -> Result<T> {
+    checker.check::<T>(q).await?;
+
+    crate::query(client, q, args).await
+}
+
+fn cardinality_fits(expected: Cardinality, described: Cardinality) -> bool {
+    use Cardinality::*;
+
+    match expected {
+        Many => true,
+        AtMostOne => matches!(described, AtMostOne | One | NoResult),
+        One => described == One,
+        AtLeastOne => matches!(described, AtLeastOne | One),
+        NoResult => described == NoResult,
+    }
+}
+
+pub(crate) fn cardinality_name(c: Cardinality) -> &'static str {
+    match c {
+        Cardinality::NoResult => "NO_RESULT",
+        Cardinality::AtMostOne => "AT_MOST_ONE",
+        Cardinality::One => "ONE",
+        Cardinality::Many => "MANY",
+        Cardinality::AtLeastOne => "AT_LEAST_ONE",
+    }
+}
+
+/// Follows custom scalars down to the base scalar they're derived from.
+pub(crate) fn base_descriptor(typedesc: &Typedesc, pos: TypePos) -> Result<&Descriptor> {
+    let mut desc = typedesc.get(pos)?;
+
+    while let Descriptor::Scalar(d) = desc {
+        desc = typedesc.get(d.base_type_pos)?;
+    }
+
+    Ok(desc)
+}
+
+pub(crate) fn describe_descriptor(desc: &Descriptor) -> String {
+    match desc {
+        Descriptor::BaseScalar(d) => base_scalar_name(&d.id)
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("scalar {}", *d.id)),
+        Descriptor::ObjectShape(_) => "an object".to_owned(),
+        Descriptor::Set(_) => "a set".to_owned(),
+        Descriptor::Tuple(_) | Descriptor::NamedTuple(_) => "a tuple".to_owned(),
+        Descriptor::Array(_) => "an array".to_owned(),
+        Descriptor::Range(_) => "a range".to_owned(),
+        Descriptor::Enumeration(_) => "an enum".to_owned(),
+        _ => format!("{desc:?}"),
+    }
+}
+
+pub(crate) fn base_scalar_name(id: &Uuid) -> Option<&'static str> {
+    Some(match *id {
+        codec::STD_UUID => "uuid",
+        codec::STD_STR => "str",
+        codec::STD_BYTES => "bytes",
+        codec::STD_INT16 => "int16",
+        codec::STD_INT32 => "int32",
+        codec::STD_INT64 => "int64",
+        codec::STD_FLOAT32 => "float32",
+        codec::STD_FLOAT64 => "float64",
+        codec::STD_DECIMAL => "decimal",
+        codec::STD_BOOL => "bool",
+        codec::STD_DATETIME => "datetime",
+        codec::STD_DURATION => "duration",
+        codec::STD_JSON => "json",
+        codec::STD_BIGINT => "bigint",
+        _ => return None,
+    })
+}
+
+/// Checks that the described value is a base scalar with this name.
+pub(crate) fn check_scalar(typedesc: &Typedesc, pos: TypePos, type_cast: &str) -> Result<()> {
+    let desc = base_descriptor(typedesc, pos)?;
+
+    match desc {
+        Descriptor::BaseScalar(d) if base_scalar_name(&d.id) == Some(type_cast) => Ok(()),
+        _ => anyhow::bail!("expected {type_cast}, got {}", describe_descriptor(desc)),
+    }
+}
+
+/// Checks that the described value is an object, and returns its element names.
+pub(crate) fn check_object(typedesc: &Typedesc, pos: TypePos) -> Result<Vec<&str>> {
+    match typedesc.get(pos)? {
+        Descriptor::ObjectShape(d) => Ok(d.elements.iter().map(|e| e.name.as_str()).collect()),
+        desc => anyhow::bail!("expected an object, got {}", describe_descriptor(desc)),
+    }
+}
+
+/// A field of an [`EdgedbObject`](crate::EdgedbObject), to check the described shape against.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectField {
+    pub name: &'static str,
+    pub cardinality: Cardinality,
+    /// Can be left out of the shape: a `#[recursive]` field past its depth, or a field of a `#[splat]`
+    pub optional: bool,
+    pub check: fn(&Typedesc, TypePos) -> Result<()>,
+}
+
+impl ObjectField {
+    pub fn new<T: EdgedbSetValue>(name: &'static str) -> Self {
+        Self {
+            name,
+            cardinality: T::EXPECTED_CARDINALITY,
+            optional: false,
+            check: T::check_element_descriptor,
+        }
+    }
+
+    /// A field that can be left out of the shape
+    pub fn optional<T: EdgedbSetValue>(name: &'static str) -> Self {
+        Self {
+            optional: true,
+            ..Self::new::<T>(name)
+        }
+    }
+}
+
+/// Checks that the described value is an object with all of these fields, of the right
+/// cardinality and type. Extra elements of the shape are ignored, and so are missing
+/// optional fields.
+pub(crate) fn check_object_fields(
+    typedesc: &Typedesc,
+    pos: TypePos,
+    fields: &[ObjectField],
+) -> Result<()> {
+    let elements = match typedesc.get(pos)? {
+        Descriptor::ObjectShape(d) => &d.elements,
+        desc => anyhow::bail!("expected an object, got {}", describe_descriptor(desc)),
+    };
+
+    for field in fields {
+        let Some(element) = elements.iter().find(|e| e.name == field.name) else {
+            if field.optional {
+                continue;
+            }
+
+            anyhow::bail!("the object has no `{}`", field.name);
+        };
+
+        if let Some(cardinality) = element.cardinality {
+            if !cardinality_fits(field.cardinality, cardinality) {
+                anyhow::bail!(
+                    "`{}` is {} but its field requires {}",
+                    field.name,
+                    cardinality_name(cardinality),
+                    cardinality_name(field.cardinality),
+                );
+            }
+        }
+
+        (field.check)(typedesc, element.type_pos)
+            .map_err(|e| e.context(format!("in `{}`", field.name)))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use edgedb_protocol::server_message::Cardinality;
+
+    use super::{cardinality_fits, QueryChecker};
+    use crate::{EdgedbObject, Ref};
+
+    #[allow(unused)]
+    #[derive(Debug, EdgedbObject)]
+    struct Inner {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[test]
+    fn cardinalities() {
+        assert!(cardinality_fits(Cardinality::One, Cardinality::One));
+        assert!(!cardinality_fits(Cardinality::One, Cardinality::AtMostOne));
+        assert!(cardinality_fits(Cardinality::AtMostOne, Cardinality::One));
+        assert!(!cardinality_fits(Cardinality::AtMostOne, Cardinality::Many));
+        assert!(cardinality_fits(Cardinality::AtLeastOne, Cardinality::One));
+        assert!(!cardinality_fits(
+            Cardinality::AtLeastOne,
+            Cardinality::Many
+        ));
+        assert!(cardinality_fits(Cardinality::Many, Cardinality::AtLeastOne));
+    }
+
+    #[tokio::test]
+    async fn some_checks() -> anyhow::Result<()> {
+        let checker = QueryChecker::from_env().await?;
+
+        checker.check::<i64>("select 7*8").await?;
+        checker
+            .check::<Vec<Inner>>("select Inner {req, opt}")
+            .await?;
+        checker
+            .check::<Option<Ref<Inner>>>("select Inner {id} limit 1")
+            .await?;
+
+        let err = checker
+            .check::<Inner>("select Inner {req, opt} limit 1")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("AT_MOST_ONE"));
+
+        assert!(checker.check::<String>("select 7*8").await.is_err());
+        assert!(checker.check::<i64>("select Inner {req}").await.is_err());
+        assert!(checker
+            .check::<Vec<Inner>>("select Inner {nope}")
+            .await
+            .is_err());
+
+        // every field has to be in the shape, of the right cardinality and type
+        assert!(checker
+            .check::<Vec<Inner>>("select Inner {req}")
+            .await
+            .is_err());
+        assert!(checker
+            .check::<Vec<Inner>>("select Inner {req := {'a', 'b'}, opt}")
+            .await
+            .is_err());
+        assert!(checker
+            .check::<Vec<Inner>>("select Inner {req := 1, opt}")
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -15,6 +15,8 @@
 //! 2. A set of tools, around the [`composable::EdgedbComposableQuery`] derivable trait, that allow you express
 //! complex, composable queries through Rust structs and attributes. See docs and examples in the [composable] submodule.
 //!
+//! The same structs can also define the schema: see [`EdgedbType`] and [`schema_sdl`].
+//!
 //! # EdgedbObject Examples
 //!
 //! If you have this schema:
@@ -177,28 +179,120 @@ pub use anyhow::Result;
 #[doc(hidden)]
 pub use itertools as __itertools;
 
+#[doc(hidden)]
+pub use inventory as __inventory;
+
 #[doc(hidden)]
 pub fn __query_add_indent(s: &str) -> String {
     s.replace('\n', "\n\t")
 }
 
+/// The fields of a `#[flatten]`ed selector, to inline in another shape
+#[doc(hidden)]
+pub fn __flatten_selector(s: &str) -> String {
+    s.trim().trim_end_matches(',').to_string()
+}
+
+/// Query text from an .edgeql file, without comments and the trailing `;`
+#[doc(hidden)]
+pub fn __query_from_file(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '#' {
+            // a comment, up to (but not including) the end of the line
+            rest = rest.find('\n').map_or("", |i| &rest[i..]);
+            continue;
+        }
+
+        let quoted = match c {
+            '\'' | '"' => {
+                // `r'...'` and `br'...'` have no escapes
+                let ident = buf.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+                let prefix = &buf[ident.len()..];
+
+                Some(quoted_len(rest, c, !matches!(prefix, "r" | "br" | "rb")))
+            }
+            '`' => Some(quoted_len(rest, '`', false)),
+            '$' => dollar_quoted_len(rest),
+            _ => None,
+        };
+
+        let len = quoted.unwrap_or(c.len_utf8());
+
+        buf.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    buf.trim().trim_end_matches(';').trim_end().to_owned()
+}
+
+/// Length of the string quoted with `q` at the start of `s`, up to the end if it's not closed.
+fn quoted_len(s: &str, q: char, escapes: bool) -> usize {
+    let mut chars = s.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        if escapes && c == '\\' {
+            chars.next();
+        } else if c == q {
+            return i + q.len_utf8();
+        }
+    }
+
+    s.len()
+}
+
+/// Length of the `$$...$$` or `$tag$...$tag$` string at the start of `s`, if there's one.
+/// Otherwise it's a parameter like `$0` or `$name`.
+fn dollar_quoted_len(s: &str) -> Option<usize> {
+    let tag_end = s[1..].find(|c: char| !(c.is_alphanumeric() || c == '_'))? + 1;
+
+    if !s[tag_end..].starts_with('$') || s[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let tag = &s[..=tag_end];
+
+    Some(
+        s[tag.len()..]
+            .find(tag)
+            .map_or(s.len(), |i| tag.len() + i + tag.len()),
+    )
+}
+
+#[doc(hidden)]
+pub use shape::ShapePlan as __ShapePlan;
+
 use edgedb_tokio::Client;
 
 mod args;
-pub use args::EdgedbQueryArgs;
+#[doc(hidden)]
+pub use args::__query_arg;
+pub use args::{EdgedbQueryArg, EdgedbQueryArgs};
+mod describe;
+pub use describe::{query_checked, ObjectField, QueryChecker, QueryDescription};
 mod prim;
 pub use prim::{EdgedbJson, EdgedbPrim};
 
 /// Express complex, composable queries through Rust structs and attributes.
 pub mod composable;
 mod refs;
+mod sdl;
+mod shape;
 mod tuples;
 mod value;
 
 extern crate self as edgedb_composable_query;
 
-pub use edgedb_composable_query_derive::EdgedbObject;
-pub use refs::Ref;
+pub use edgedb_composable_query_derive::{EdgedbObject, EdgedbType};
+pub use refs::{IdOnly, Ref};
+pub use sdl::{
+    registered_types, schema_sdl, EdgedbSdlElement, EdgedbSdlField, EdgedbType, RegisteredType,
+};
+
+#[doc(hidden)]
+pub use sdl::{__sdl_computed, __sdl_index, __sdl_pointer, __sdl_type, SdlPointerAttrs};
 
 use edgedb_protocol::{codec::ObjectShape, value::Value};
 
@@ -209,6 +303,21 @@ pub use value::{EdgedbSetValue, EdgedbValue};
 /// Struct that can be received from EdgeDB as an Object. Derive this trait for your structs.
 pub trait EdgedbObject: Sized {
     fn from_edgedb_object(shape: ObjectShape, fields: Vec<Option<Value>>) -> Result<Self>;
+
+    /// Like [`EdgedbObject::from_edgedb_object`], but leaves the fields it doesn't need, for `#[flatten]`.
+    /// The derived impls only take their own fields, this one clones them.
+    #[doc(hidden)]
+    fn __take_from_edgedb_object(
+        shape: &ObjectShape,
+        fields: &mut [Option<Value>],
+    ) -> Result<Self> {
+        Self::from_edgedb_object(shape.clone(), fields.to_vec())
+    }
+
+    /// The fields that [`QueryChecker`] looks for in the described shape. None by default: any object is accepted.
+    fn object_fields() -> Vec<ObjectField> {
+        Vec::new()
+    }
     // fn to_edgedb_object(&self) -> Result<(ObjectShape, Vec<Option<Value>>)>;
 }
 
@@ -226,6 +335,38 @@ pub async fn query<T: EdgedbSetValue, Args: EdgedbQueryArgs + Send>(
 mod test {
     use crate::{query, EdgedbObject, EdgedbSetValue};
 
+    #[test]
+    fn query_from_file() {
+        assert_eq!(
+            crate::__query_from_file(
+                "# comment\nselect Inner # another one\nfilter .req = '#not a comment';  # trailing\n\n"
+            ),
+            "select Inner \nfilter .req = '#not a comment'"
+        );
+
+        // no escapes in raw strings
+        assert_eq!(
+            crate::__query_from_file(r"select r'C:\' ++ '#not a comment' # comment"),
+            r"select r'C:\' ++ '#not a comment'"
+        );
+        assert_eq!(
+            crate::__query_from_file(r#"select 'it\'s # not a comment' # comment"#),
+            r#"select 'it\'s # not a comment'"#
+        );
+        assert_eq!(
+            crate::__query_from_file("select $$it's # not a comment$$ # comment"),
+            "select $$it's # not a comment$$"
+        );
+        assert_eq!(
+            crate::__query_from_file("select $a$ $$ # not a comment $a$ ++ <str>$0 # comment"),
+            "select $a$ $$ # not a comment $a$ ++ <str>$0"
+        );
+        assert_eq!(
+            crate::__query_from_file("select <str>$name # comment\n;"),
+            "select <str>$name"
+        );
+    }
+
     #[derive(Debug, PartialEq, EdgedbObject)]
     struct ExamplImplStruct {
         a: String,
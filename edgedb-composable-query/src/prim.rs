@@ -1,6 +1,7 @@
 use crate::value::EdgedbValue;
 use crate::Result;
-use edgedb_protocol::model::{Json, Uuid};
+use edgedb_protocol::descriptors::{TypePos, Typedesc};
+use edgedb_protocol::model::{Datetime, Json, Uuid};
 use edgedb_protocol::value::{self, Value};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
@@ -39,6 +40,10 @@ macro_rules! impl_prim {
                     <$t>::from_edgedb_val(value)
                 }
 
+                fn check_descriptor(typedesc: &Typedesc, pos: TypePos) -> Result<()> {
+                    crate::describe::check_scalar(typedesc, pos, $name)
+                }
+
                 // fn to_edgedb_value(self) -> Result<Value> {
                 //     <$t>::to_edgedb_val(self)
                 // }
@@ -58,7 +63,8 @@ impl_prim! {
     f64 => Float64 "float64",
     bool => Bool "bool",
     String => Str "str",
-    Uuid => Uuid "uuid"
+    Uuid => Uuid "uuid",
+    Datetime => Datetime "datetime"
 }
 
 /// Wrapper around your serializable types to pass them as JSON query arguments
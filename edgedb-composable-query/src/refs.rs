@@ -1,10 +1,14 @@
-use edgedb_protocol::{model::Uuid, value::Value};
+use edgedb_protocol::{
+    descriptors::{TypePos, Typedesc},
+    model::Uuid,
+    value::Value,
+};
 use itertools::Itertools;
 
 use crate::{prim::EdgedbPrim, value::EdgedbValue, EdgedbObject};
 
 /// Basically a `Uuid` and an `Option<T>`. Use this instead of adding `id: Uuid` field to your structs.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Ref<T: EdgedbObject> {
     pub id: Uuid,
     pub known_value: Option<T>,
@@ -70,11 +74,68 @@ impl<T: EdgedbObject> EdgedbValue for Ref<T> {
     // fn to_edgedb_value(self) -> anyhow::Result<edgedb_protocol::value::Value> {
     //     todo!()
     // }
+
+    fn check_descriptor(typedesc: &Typedesc, pos: TypePos) -> anyhow::Result<()> {
+        let names = crate::describe::check_object(typedesc, pos)?;
+
+        if !names.contains(&"id") {
+            anyhow::bail!("expected an object with an 'id' field for a Ref");
+        }
+
+        Ok(())
+    }
+}
+
+/// For links you only need the `id` of: `Ref<IdOnly>` selects nothing but the `id`,
+/// and its `known_value` is always `None`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IdOnly {}
+
+impl EdgedbObject for IdOnly {
+    fn from_edgedb_object(
+        _shape: edgedb_protocol::codec::ObjectShape,
+        _fields: Vec<Option<Value>>,
+    ) -> anyhow::Result<Self> {
+        anyhow::bail!("IdOnly has no value, use Ref<IdOnly> to decode just the id")
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{value::EdgedbSetValue, EdgedbObject};
+    use edgedb_protocol::{
+        codec::{ObjectShape, ShapeElement},
+        model::Uuid,
+        value::Value,
+    };
+
+    use super::IdOnly;
+    use crate::{value::EdgedbSetValue, EdgedbObject, Ref};
+
+    #[test]
+    fn id_only_ref() {
+        let id = Uuid::from_u128(7);
+        let shape = ObjectShape::new(vec![ShapeElement {
+            flag_implicit: false,
+            flag_link_property: false,
+            flag_link: false,
+            cardinality: None,
+            name: "id".to_string(),
+        }]);
+
+        let r = Ref::<IdOnly>::from_edgedb_set_value(Value::Object {
+            shape,
+            fields: vec![Some(Value::Uuid(id))],
+        })
+        .unwrap();
+
+        assert_eq!(
+            r,
+            Ref {
+                id,
+                known_value: None
+            }
+        );
+    }
 
     #[derive(Debug, PartialEq)]
     struct Inner {
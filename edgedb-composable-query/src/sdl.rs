@@ -0,0 +1,248 @@
+use edgedb_protocol::server_message::Cardinality;
+use nonempty::NonEmpty;
+
+use crate::{EdgedbPrim, EdgedbSetValue, Ref};
+
+/// An object type defined by a Rust struct. Derivable, see [`schema_sdl`].
+///
+/// Fields are properties or links, `required` unless they're `Option`, `multi` if they're
+/// `Vec` or [`NonEmpty`]. `#[var("...")]` fields are computed, and `id` is skipped.
+///
+/// - `#[edgedb(sdl_type = "Name")]`: the type name, the struct's name by default. Unlike
+///   `#[edgedb(type = "Name")]`, which works too, a selector isn't checked against
+///   `dbschema` with it, since the type isn't there until the SDL is written
+/// - `#[edgedb(index = ".field")]`: `index on (.field)`, can be repeated
+/// - `#[edgedb(exclusive)]` on a field: `constraint exclusive`
+/// - `#[edgedb(default = "expr")]` on a field: `default := expr`
+/// - `#[edgedb(required)]`, `#[edgedb(multi)]` on a field: override the cardinality
+pub trait EdgedbType {
+    const TYPE_NAME: &'static str;
+
+    /// `type Name { ... }`
+    fn sdl() -> String;
+}
+
+/// What a property or a link points to: a scalar, or another [`EdgedbType`].
+pub trait EdgedbSdlElement {
+    const SDL_TARGET: &'static str;
+}
+
+impl<T: EdgedbPrim> EdgedbSdlElement for T {
+    const SDL_TARGET: &'static str = T::TYPE_CAST;
+}
+
+impl<T: EdgedbType + crate::EdgedbObject> EdgedbSdlElement for Ref<T> {
+    const SDL_TARGET: &'static str = T::TYPE_NAME;
+}
+
+/// [`EdgedbSdlElement`] of a field, through `Option`, `Vec` and [`NonEmpty`].
+pub trait EdgedbSdlField {
+    const SDL_TARGET: &'static str;
+}
+
+impl<T: EdgedbSdlElement> EdgedbSdlField for T {
+    const SDL_TARGET: &'static str = T::SDL_TARGET;
+}
+
+impl<T: EdgedbSdlElement> EdgedbSdlField for Option<T> {
+    const SDL_TARGET: &'static str = T::SDL_TARGET;
+}
+
+impl<T: EdgedbSdlElement> EdgedbSdlField for Vec<T> {
+    const SDL_TARGET: &'static str = T::SDL_TARGET;
+}
+
+impl<T: EdgedbSdlElement> EdgedbSdlField for NonEmpty<T> {
+    const SDL_TARGET: &'static str = T::SDL_TARGET;
+}
+
+/// `#[edgedb(...)]` of a field, for [`__sdl_pointer`]
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct SdlPointerAttrs {
+    pub required: bool,
+    pub multi: bool,
+    pub exclusive: bool,
+    pub default: Option<&'static str>,
+}
+
+/// `required`/`multi` come from the field's cardinality, unless forced by the attributes
+#[doc(hidden)]
+pub fn __sdl_pointer<T: EdgedbSetValue + EdgedbSdlField>(
+    name: &str,
+    attrs: SdlPointerAttrs,
+) -> String {
+    let (required, multi) = match T::EXPECTED_CARDINALITY {
+        Cardinality::One => (true, false),
+        Cardinality::AtLeastOne => (true, true),
+        Cardinality::Many => (false, true),
+        Cardinality::AtMostOne | Cardinality::NoResult => (false, false),
+    };
+
+    let mut buf = String::from("    ");
+
+    if required || attrs.required {
+        buf.push_str("required ");
+    }
+    if multi || attrs.multi {
+        buf.push_str("multi ");
+    }
+
+    buf.push_str(&format!("{name}: {}", T::SDL_TARGET));
+
+    if attrs.exclusive || attrs.default.is_some() {
+        buf.push_str(" {\n");
+
+        if attrs.exclusive {
+            buf.push_str("        constraint exclusive;\n");
+        }
+        if let Some(default) = attrs.default {
+            buf.push_str(&format!("        default := {default};\n"));
+        }
+
+        buf.push_str("    }");
+    }
+
+    buf.push_str(";\n");
+    buf
+}
+
+#[doc(hidden)]
+pub fn __sdl_computed(name: &str, expr: &str) -> String {
+    format!("    {name} := ({expr});\n")
+}
+
+#[doc(hidden)]
+pub fn __sdl_index(expr: &str) -> String {
+    format!("    index on ({expr});\n")
+}
+
+#[doc(hidden)]
+pub fn __sdl_type(name: &str, body: &str) -> String {
+    format!("type {name} {{\n{body}}}\n")
+}
+
+/// An [`EdgedbType`], registered automatically by its derive. See [`schema_sdl`].
+pub struct RegisteredType {
+    pub type_name: &'static str,
+    pub sdl: fn() -> String,
+}
+
+inventory::collect!(RegisteredType);
+
+impl RegisteredType {
+    #[doc(hidden)]
+    pub const fn new<T: EdgedbType>() -> Self {
+        Self {
+            type_name: T::TYPE_NAME,
+            sdl: T::sdl,
+        }
+    }
+}
+
+/// All the types derived in the binary, sorted by their names.
+pub fn registered_types() -> Vec<&'static RegisteredType> {
+    let mut types = inventory::iter::<RegisteredType>
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    types.sort_by_key(|t| t.type_name);
+
+    types
+}
+
+/// `module default { ... }` with every registered [`EdgedbType`], to be written to
+/// `dbschema/default.esdl`:
+///
+/// ```
+/// use edgedb_composable_query::{schema_sdl, EdgedbType};
+///
+/// #[derive(EdgedbType)]
+/// struct User {
+///     #[edgedb(exclusive)]
+///     name: String,
+///     nickname: Option<String>,
+/// }
+///
+/// assert!(schema_sdl().contains(
+///     "    type User {\n        required name: str {\n            constraint exclusive;\n        };\n"
+/// ));
+/// ```
+pub fn schema_sdl() -> String {
+    let mut buf = String::from("module default {\n");
+
+    for (i, ty) in registered_types().into_iter().enumerate() {
+        if i > 0 {
+            buf.push('\n');
+        }
+
+        for line in (ty.sdl)().lines() {
+            match line {
+                "" => buf.push('\n'),
+                _ => buf.push_str(&format!("    {line}\n")),
+            }
+        }
+    }
+
+    buf.push_str("}\n");
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use edgedb_composable_query_schema::esdl::Schema;
+
+    use crate::{EdgedbObject, EdgedbType, Ref};
+
+    #[allow(unused)]
+    #[derive(EdgedbType, EdgedbObject)]
+    struct Inner {
+        req: String,
+        opt: Option<String>,
+    }
+
+    #[allow(unused)]
+    #[derive(EdgedbType)]
+    #[edgedb(index = ".other_field")]
+    struct Outer {
+        inner: Option<Ref<Inner>>,
+
+        #[edgedb(exclusive)]
+        other_field: String,
+        #[edgedb(default = "'nothing'")]
+        some_field: Option<String>,
+
+        a: Option<Inner>,
+        b: Option<Ref<Inner>>,
+
+        #[var("len(.other_field)")]
+        other_len: i64,
+    }
+
+    #[test]
+    fn type_sdl() {
+        insta::assert_snapshot!(Outer::sdl());
+    }
+
+    #[test]
+    fn matches_dbschema() {
+        let mut from_rust = Schema::default();
+        from_rust.parse_into(&super::schema_sdl()).unwrap();
+
+        let mut dbschema = Schema::default();
+        dbschema
+            .parse_into(include_str!("../../dbschema/default.esdl"))
+            .unwrap();
+
+        for name in ["Inner", "Outer"] {
+            let mut ours = from_rust.object_type(name).unwrap().pointers.clone();
+            ours.retain(|p| !p.computed);
+            ours.sort_by(|a, b| a.name.cmp(&b.name));
+
+            let mut theirs = dbschema.object_type(name).unwrap().pointers.clone();
+            theirs.sort_by(|a, b| a.name.cmp(&b.name));
+
+            assert_eq!(ours, theirs);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::{cell::RefCell, rc::Rc, thread::LocalKey};
+
+use edgedb_protocol::codec::{ObjectShape, ObjectShapeInfo};
+
+/// How many shapes a type keeps plans for: a type can be decoded from several shapes in the
+/// same row, like two links to the same type with different subqueries.
+const CACHED_SHAPES: usize = 8;
+
+/// Precomputed mapping from struct fields to the elements of an [`ObjectShape`].
+///
+/// All rows of a query result share the same `Arc`'ed shapes, so the mapping is computed once
+/// for the first row, and the following rows reuse it by pointer equality. Used by the
+/// `EdgedbObject` derive.
+#[doc(hidden)]
+pub struct ShapePlan {
+    // keeping the shape alive guarantees the pointer won't be reused by another shape
+    shape: ObjectShape,
+    indices: Rc<[Option<usize>]>,
+}
+
+impl ShapePlan {
+    pub fn new(shape: &ObjectShape, field_names: &[&str]) -> Self {
+        let indices = field_names
+            .iter()
+            .map(|name| shape.elements.iter().position(|e| e.name == *name))
+            .collect();
+
+        Self {
+            shape: shape.clone(),
+            indices,
+        }
+    }
+
+    pub fn is_for(&self, shape: &ObjectShape) -> bool {
+        std::ptr::eq::<ObjectShapeInfo>(&*self.shape, &**shape)
+    }
+
+    /// For each of `field_names`, the index of the shape element with this name, if any.
+    ///
+    /// The cache keeps the plans of the last [`CACHED_SHAPES`] shapes, the most recent first.
+    pub fn cached(
+        cache: &'static LocalKey<RefCell<Vec<ShapePlan>>>,
+        shape: &ObjectShape,
+        field_names: &[&str],
+    ) -> Rc<[Option<usize>]> {
+        cache.with(|cache| {
+            let mut cache = cache.borrow_mut();
+
+            if let Some(plan) = cache.iter().find(|plan| plan.is_for(shape)) {
+                return plan.indices.clone();
+            }
+
+            let plan = ShapePlan::new(shape, field_names);
+            let indices = plan.indices.clone();
+
+            cache.truncate(CACHED_SHAPES - 1);
+            cache.insert(0, plan);
+
+            indices
+        })
+    }
+}
+
+/// A shape with these element names, for the decoding tests
+#[cfg(test)]
+pub(crate) fn test_shape(names: &[&str]) -> ObjectShape {
+    ObjectShape::new(
+        names
+            .iter()
+            .map(|n| edgedb_protocol::codec::ShapeElement {
+                flag_implicit: false,
+                flag_link_property: false,
+                flag_link: false,
+                cardinality: None,
+                name: n.to_string(),
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::{test_shape as shape, ShapePlan, CACHED_SHAPES};
+
+    thread_local! {
+        static PLAN: RefCell<Vec<ShapePlan>> = const { RefCell::new(Vec::new()) };
+    }
+
+    #[test]
+    fn plan_is_reused_for_the_same_shape() {
+        let first = shape(&["id", "b", "a"]);
+
+        let indices = ShapePlan::cached(&PLAN, &first, &["a", "b", "c"]);
+        assert_eq!(&*indices, &[Some(2), Some(1), None]);
+
+        let again = ShapePlan::cached(&PLAN, &first.clone(), &["a", "b", "c"]);
+        assert!(std::rc::Rc::ptr_eq(&indices, &again));
+
+        // equal, but not the same shape
+        let second = shape(&["id", "b", "a"]);
+        let other = ShapePlan::cached(&PLAN, &second, &["a", "b", "c"]);
+        assert!(!std::rc::Rc::ptr_eq(&indices, &other));
+        assert_eq!(&*indices, &*other);
+
+        // both are kept, for a type decoded from two shapes in every row
+        assert!(std::rc::Rc::ptr_eq(
+            &indices,
+            &ShapePlan::cached(&PLAN, &first, &["a", "b", "c"])
+        ));
+        assert!(std::rc::Rc::ptr_eq(
+            &other,
+            &ShapePlan::cached(&PLAN, &second, &["a", "b", "c"])
+        ));
+
+        let third = shape(&["c"]);
+        assert_eq!(
+            &*ShapePlan::cached(&PLAN, &third, &["a", "b", "c"]),
+            &[None, None, Some(0)]
+        );
+
+        // the oldest plans are dropped
+        for _ in 0..CACHED_SHAPES {
+            ShapePlan::cached(&PLAN, &shape(&["a"]), &["a", "b", "c"]);
+        }
+        assert!(!std::rc::Rc::ptr_eq(
+            &indices,
+            &ShapePlan::cached(&PLAN, &first, &["a", "b", "c"])
+        ));
+    }
+}
@@ -3,6 +3,7 @@ use crate::{args::EdgedbQueryArgs, EdgedbObject};
 use edgedb_tokio::Client;
 pub use nonempty::{nonempty, NonEmpty};
 
+use edgedb_protocol::descriptors::{TypePos, Typedesc};
 use edgedb_protocol::value::Value;
 
 /// An object or a primitive. For sets, see [`EdgedbSetValue`]. Provided automatically in most cases.
@@ -12,6 +13,11 @@ pub trait EdgedbValue: Sized {
 
     fn from_edgedb_value(value: Value) -> Result<Self>;
     // fn to_edgedb_value(self) -> Result<Value>;
+
+    /// Check the type the server described for this value (see [`crate::QueryChecker`]). Accepts anything by default.
+    fn check_descriptor(_typedesc: &Typedesc, _pos: TypePos) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// A value of a particular cardinality (typically it's provided automatically for `T`, `Option<T>`, `Vec<T>` or [`NonEmpty<T>`])
@@ -23,6 +29,11 @@ pub trait EdgedbSetValue: Sized {
 
     fn interpret_possibly_missing_required_value(val: Option<Self>) -> Result<Self>;
 
+    /// Check the type the server described for the elements of this set. Accepts anything by default.
+    fn check_element_descriptor(_typedesc: &Typedesc, _pos: TypePos) -> Result<()> {
+        Ok(())
+    }
+
     fn query_direct<Args: EdgedbQueryArgs + Send>(
         client: &Client,
         q: &str,
@@ -41,6 +52,10 @@ impl<T: EdgedbObject> EdgedbValue for T {
         Self::from_edgedb_object(shape, fields)
     }
 
+    fn check_descriptor(typedesc: &Typedesc, pos: TypePos) -> Result<()> {
+        crate::describe::check_object_fields(typedesc, pos, &T::object_fields())
+    }
+
     // fn to_edgedb_value(self) -> Result<Value> {
     //     let (shape, fields) = self.to_edgedb_object()?;
     //     Ok(Value::Object { shape, fields })
@@ -71,6 +86,10 @@ impl<T: EdgedbValue> EdgedbSetValue for T {
     //     T::to_edgedb_value(self)
     // }
 
+    fn check_element_descriptor(typedesc: &Typedesc, pos: TypePos) -> Result<()> {
+        T::check_descriptor(typedesc, pos)
+    }
+
     fn interpret_possibly_missing_required_value(val: Option<Self>) -> Result<Self> {
         match val {
             Some(val) => Ok(val),
@@ -109,6 +128,10 @@ impl<T: EdgedbValue> EdgedbSetValue for Option<T> {
     //     }
     // }
 
+    fn check_element_descriptor(typedesc: &Typedesc, pos: TypePos) -> Result<()> {
+        T::check_descriptor(typedesc, pos)
+    }
+
     fn interpret_possibly_missing_required_value(val: Option<Self>) -> Result<Self> {
         Ok(val.flatten())
     }
@@ -165,6 +188,10 @@ impl<T: EdgedbValue> EdgedbSetValue for Vec<T> {
         Ok(val)
     }
 
+    fn check_element_descriptor(typedesc: &Typedesc, pos: TypePos) -> Result<()> {
+        T::check_descriptor(typedesc, pos)
+    }
+
     fn interpret_possibly_missing_required_value(val: Option<Self>) -> Result<Self> {
         Ok(val.unwrap_or_default())
     }
@@ -222,6 +249,10 @@ impl<T: EdgedbValue> EdgedbSetValue for NonEmpty<T> {
         NonEmpty::from_vec(val).ok_or_else(|| anyhow::anyhow!("expected non-empty set"))
     }
 
+    fn check_element_descriptor(typedesc: &Typedesc, pos: TypePos) -> Result<()> {
+        T::check_descriptor(typedesc, pos)
+    }
+
     fn interpret_possibly_missing_required_value(val: Option<Self>) -> Result<Self> {
         val.ok_or_else(|| anyhow::anyhow!("expected non-empty set"))
     }
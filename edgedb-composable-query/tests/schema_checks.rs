@@ -0,0 +1,8 @@
+//! The compile-time checks of `#[edgedb(type = "...")]` selectors against `dbschema/*.esdl`
+
+#[test]
+fn schema_checks() {
+    let t = trybuild::TestCases::new();
+
+    t.compile_fail("tests/ui/*.rs");
+}
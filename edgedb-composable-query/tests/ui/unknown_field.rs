@@ -0,0 +1,10 @@
+use edgedb_composable_query::{composable::EdgedbComposableSelector, EdgedbObject};
+
+#[derive(EdgedbObject, EdgedbComposableSelector)]
+#[edgedb(type = "Inner")]
+struct InnerSelector {
+    req: String,
+    missing: Option<String>,
+}
+
+fn main() {}
@@ -0,0 +1,66 @@
+//! Writes every composable query of this example to `queries/<TypeName>.edgeql`,
+//! so the generated EdgeQL can be reviewed in diffs, or analyzed with `edgedb` tooling.
+//!
+//! ```sh
+//! cargo run -p example -- [output dir]
+//! ```
+//!
+//! `schema.rs` is generated from `dbschema` with:
+//!
+//! ```sh
+//! cargo run -p edgedb-composable-query-codegen -- dbschema -o example/src/schema.rs
+//! ```
+
+// generated for every type in the schema, not all of them are queried here
+#[allow(dead_code)]
+mod schema;
+
+use edgedb_composable_query::{
+    composable::{export_queries, EdgedbComposableQuery, EdgedbComposableSelector},
+    EdgedbObject, Ref,
+};
+use edgedb_protocol::model::Uuid;
+
+#[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+pub struct InnerSelector {
+    req: String,
+    opt: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+#[params(id: Uuid)]
+#[select("select Inner filter .id = id")]
+pub struct InnerById(Option<InnerSelector>);
+
+#[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+#[select("select Inner limit 10")]
+pub struct SomeInners(Vec<InnerSelector>);
+
+#[derive(Debug, PartialEq, Eq, EdgedbObject, EdgedbComposableSelector)]
+pub struct OuterSelector {
+    inner: Option<Ref<InnerSelector>>,
+
+    some_field: Option<String>,
+    other_field: String,
+}
+
+#[derive(Debug, PartialEq, Eq, EdgedbComposableQuery)]
+#[params(id: Uuid)]
+#[select("select Outer filter .id = id limit 1")]
+pub struct OuterById(Option<OuterSelector>);
+
+#[derive(Debug, PartialEq, EdgedbComposableQuery)]
+#[select("select Outer limit 10")]
+pub struct SomeOuters(Vec<schema::Outer>);
+
+fn main() -> std::io::Result<()> {
+    let dir = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "queries".to_string());
+
+    for path in export_queries(dir)? {
+        println!("{}", path.display());
+    }
+
+    Ok(())
+}
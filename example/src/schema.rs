@@ -0,0 +1,50 @@
+// Generated by edgedb-composable-query-codegen, don't edit by hand.
+
+use edgedb_composable_query::{composable::EdgedbComposableSelector, EdgedbObject, IdOnly, Ref};
+use edgedb_protocol::model::Uuid;
+
+/// `Article`
+#[derive(Debug, PartialEq, EdgedbObject, EdgedbComposableSelector)]
+pub struct Article {
+    pub id: Uuid,
+    pub title: String,
+    pub related: Vec<Ref<Content>>,
+    pub body: Option<String>,
+}
+
+/// `Content`
+#[derive(Debug, PartialEq, EdgedbObject, EdgedbComposableSelector)]
+pub struct Content {
+    pub id: Uuid,
+    pub title: String,
+    /// link to [`Content`], only the `id`: it links back to `Content`
+    pub related: Vec<Ref<IdOnly>>,
+}
+
+/// `Inner`
+#[derive(Debug, PartialEq, EdgedbObject, EdgedbComposableSelector)]
+pub struct Inner {
+    pub id: Uuid,
+    pub req: String,
+    pub opt: Option<String>,
+}
+
+/// `Outer`
+#[derive(Debug, PartialEq, EdgedbObject, EdgedbComposableSelector)]
+pub struct Outer {
+    pub id: Uuid,
+    pub inner: Option<Ref<Inner>>,
+    pub other_field: String,
+    pub some_field: Option<String>,
+    pub a: Option<Ref<Inner>>,
+    pub b: Option<Ref<Inner>>,
+}
+
+/// `Video`
+#[derive(Debug, PartialEq, EdgedbObject, EdgedbComposableSelector)]
+pub struct Video {
+    pub id: Uuid,
+    pub title: String,
+    pub related: Vec<Ref<Content>>,
+    pub duration: Option<i64>,
+}